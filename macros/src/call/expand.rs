@@ -1,9 +1,14 @@
-use super::parse::CallDef;
-use quote::quote;
+use super::parse::{ CallCaller, CallDef, CallReceiver };
+use quote::{ quote, ToTokens };
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-    let CallDef { pallet_struct, methods } = def;
+    let CallDef { pallet_type, generics, methods } = def;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // The predicates of the where-clause of the `impl`, which we extend in the trait impls below.
+    let where_predicates = where_clause
+        .map(|where_clause| where_clause.predicates.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
 
     // This is a vector of all the callable function names.
     let fn_name = methods
@@ -11,9 +16,22 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
         .map(|method| &method.name)
         .collect::<Vec<_>>();
 
+    // These are the attributes kept from each of the functions in `fn_name`: its `#[cfg]`
+    // attributes, added to everything generated for the function, and its doc comments and
+    // `#[deprecated]` attribute, added to its variant and constructor. Since the generated code
+    // uses deprecated variants itself, it allows them.
+    let fn_cfg = methods
+        .iter()
+        .map(|method| &method.cfg_attrs)
+        .collect::<Vec<_>>();
+    let fn_attrs = methods
+        .iter()
+        .map(|method| &method.variant_attrs)
+        .collect::<Vec<_>>();
+
     // This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
-    // not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
-    // first two parameters to these calls.
+    // not include the `self` (or `runtime`) or `caller: T::AccountId` parameter, which we always
+    // assume are the first two parameters to these calls.
     let args_name = methods
         .iter()
         .map(|method|
@@ -36,43 +54,381 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
         )
         .collect::<Vec<_>>();
 
+    // This is a vector of the expressions used to invoke each of the functions in `fn_name`, with
+    // all of their arguments. Functions taking `&mut self` are called on the pallet, which we get
+    // out of the runtime, while functions taking `runtime: &mut T` are given the whole runtime.
+    // Functions taking `caller: T::AccountId` can only be called by a signed origin, while
+    // functions taking `origin` are given the origin as is.
+    let fn_invoke = methods
+        .iter()
+        .zip(args_name.iter())
+        .map(|(method, args_name)| {
+            let name = &method.name;
+            let caller = match method.caller {
+                CallCaller::Signed => quote! { origin.ensure_signed()? },
+                CallCaller::Origin => quote! { origin },
+            };
+            match method.receiver {
+                CallReceiver::Pallet =>
+                    quote! {
+					AsMut::<#pallet_type>::as_mut(runtime).#name(#caller, #( #args_name ),*)
+				},
+                CallReceiver::Runtime =>
+                    quote! {
+					<#pallet_type>::#name(runtime, #caller, #( #args_name ),*)
+				},
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // The type parameters of the pallet which no argument uses, like an instance `I`. Since `Call`
+    // has the same generics as the `impl`, these are kept in a hidden variant, which can never be
+    // created since it contains `Infallible`. Arguments of functions with `#[cfg]` attributes do
+    // not count, since they may be compiled out.
+    let unused_params = generics
+        .type_params()
+        .map(|param| &param.ident)
+        .filter(|ident| {
+            !methods
+                .iter()
+                .filter(|method| method.cfg_attrs.is_empty())
+                .flat_map(|method| &method.args)
+                .any(|(_, type_)| mentions_ident(type_.to_token_stream(), ident))
+        })
+        .collect::<Vec<_>>();
+
+    // Two functions with the same index are only an error if both are compiled in. When either
+    // has `#[cfg]` attributes, the parser cannot tell, so this emits the error under the `#[cfg]`
+    // attributes of both, where it only fires if neither is compiled out.
+    let duplicate_index_checks = methods
+        .iter()
+        .enumerate()
+        .flat_map(|(i, first)| methods[i + 1..].iter().map(move |second| (first, second)))
+        .filter(|(first, second)| first.index == second.index)
+        .map(|(first, second)| {
+            let (first_cfg, second_cfg) = (&first.cfg_attrs, &second.cfg_attrs);
+            let msg = format!("Duplicate call index {}, used by `{}`", second.index, first.name);
+            quote::quote_spanned! { second.name.span() =>
+				#( #first_cfg )*
+				#( #second_cfg )*
+				const _: () = { compile_error!(#msg); };
+			}
+        })
+        .collect::<Vec<_>>();
+    let (ignore_variant, ignore_arm, ignore_arm_owned, ignore_arm_pair) = if
+        unused_params.is_empty()
+    {
+        (quote! {}, quote! {}, quote! {}, quote! {})
+    } else {
+        (
+            quote! {
+				#[doc(hidden)]
+				__Ignore(
+					core::marker::PhantomData<( #( #unused_params, )* )>,
+					core::convert::Infallible,
+				),
+			},
+            quote! { Call::__Ignore(_, never) => match *never {}, },
+            quote! { Call::__Ignore(_, never) => match never {}, },
+            quote! { (Call::__Ignore(_, never), _) => match *never {}, },
+        )
+    };
+
+    // The generics of the `enum Call` are those of the `impl`. The second type parameter, if any,
+    // is the instance of the pallet, which defaults to `()` like on the pallet struct, so that
+    // `Call<T>` is the call of the default instance.
+    let mut enum_generics = generics.clone();
+    if let Some(instance) = enum_generics.type_params_mut().nth(1) {
+        instance.eq_token = Some(Default::default());
+        instance.default = Some(syn::parse_quote!(()));
+    }
+
     // This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
-    // and the `Dispatch` trait logic to route a `caller` to access those functions.
+    // and the dispatch logic to route a `caller` to access those functions.
     let dispatch_impl =
         quote! {
 		// The callable functions exposed by this pallet.
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
-		pub enum Call<T: Config> {
+		pub enum Call #enum_generics #where_clause {
 			#(
+				#( #fn_cfg )*
+				#( #fn_attrs )*
 				#fn_name { #( #args_name: #args_type),* },
 			)*
+			#ignore_variant
 		}
 
 		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
-		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
-			type Call = Call<T>;
-
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
-				match call {
+		// appropriate function call with all arguments, including the `caller` or `origin`.
+		//
+		// Calls are dispatched against the runtime `T` which includes this pallet, so that functions
+		// can access other pallets or dispatch further calls.
+		#[allow(deprecated)]
+		impl #impl_generics Call #ty_generics #where_clause {
+			pub fn dispatch(
+				self,
+				runtime: &mut T,
+				origin: crate::support::Origin<T::AccountId>,
+			) -> crate::support::DispatchResult
+			where
+				T: AsMut<#pallet_type>,
+			{
+				match self {
 					#(
+						#( #fn_cfg )*
 						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
-								#( #args_name ),*
-							)?;
+							// Note that we assume the second argument of every call is the `caller`
+							// or `origin`.
+							#fn_invoke?;
 						},
 					)*
+					#ignore_arm_owned
 				}
 				Ok(())
 			}
 		}
 	};
 
+    // Other names for the arguments of each function in `fn_name`, to compare two calls.
+    let other_args_name = args_name
+        .iter()
+        .map(|names|
+            names
+                .iter()
+                .enumerate()
+                .map(|(i, _)| quote::format_ident!("__other_{}", i))
+                .collect::<Vec<_>>()
+        )
+        .collect::<Vec<_>>();
+
+    // The names of the constructors of each of the functions in `fn_name`.
+    let fn_constructor = fn_name
+        .iter()
+        .map(|name| quote::format_ident!("new_{}", name))
+        .collect::<Vec<_>>();
+
+    // This quote block creates a constructor for each call, taking the arguments of the function in
+    // order, like `Call::new_transfer(to, amount)`.
+    let constructors_impl =
+        quote! {
+		#[allow(deprecated)]
+		impl #impl_generics Call #ty_generics #where_clause {
+			#(
+				#( #fn_cfg )*
+				#( #fn_attrs )*
+				pub fn #fn_constructor(#( #args_name: #args_type ),*) -> Self {
+					Call::#fn_name { #( #args_name ),* }
+				}
+			)*
+		}
+	};
+
+    // This quote block implements `Clone`, `Debug`, `PartialEq` and `Eq` for the `enum Call`. We
+    // cannot derive them, since the derive would require `T: Clone` and `T: Debug`, while we only
+    // need the arguments of each call to implement these traits.
+    let traits_impl =
+        quote! {
+		#[allow(deprecated)]
+		impl #impl_generics Clone for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: Clone, )* )*
+		{
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						#( #fn_cfg )*
+						Call::#fn_name { #( #args_name ),* } => Call::#fn_name {
+							#( #args_name: #args_name.clone() ),*
+						},
+					)*
+					#ignore_arm
+				}
+			}
+		}
+
+		#[allow(deprecated)]
+		impl #impl_generics PartialEq for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: PartialEq, )* )*
+		{
+			// Calls are equal when they call the same function with equal arguments. The arguments
+			// of `other` are renamed, so that they do not clash with the arguments of `self`.
+			#[allow(unreachable_patterns)]
+			fn eq(&self, other: &Self) -> bool {
+				match (self, other) {
+					#(
+						#( #fn_cfg )*
+						(
+							Call::#fn_name { #( #args_name ),* },
+							Call::#fn_name { #( #args_name: #other_args_name ),* },
+						) => true #( && #args_name == #other_args_name )*,
+					)*
+					#ignore_arm_pair
+					_ => false,
+				}
+			}
+		}
+
+		impl #impl_generics Eq for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: Eq, )* )*
+		{}
+
+		#[allow(deprecated)]
+		impl #impl_generics core::fmt::Debug for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: core::fmt::Debug, )* )*
+		{
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						#( #fn_cfg )*
+						Call::#fn_name { #( #args_name ),* } => f
+							.debug_struct(stringify!(#fn_name))
+							#( .field(stringify!(#args_name), #args_name) )*
+							.finish(),
+					)*
+					#ignore_arm
+				}
+			}
+		}
+	};
+
+    // These are the index, doc comments and argument type names of each of the functions in
+    // `fn_name`, for the encoding and metadata of calls.
+    let fn_index = methods
+        .iter()
+        .map(|method| method.index)
+        .collect::<Vec<_>>();
+    let fn_docs = methods
+        .iter()
+        .map(|method| &method.docs)
+        .collect::<Vec<_>>();
+    let fn_deprecated = methods
+        .iter()
+        .map(|method| match &method.deprecated {
+            Some(note) => quote! { Some(#note) },
+            None => quote! { None },
+        })
+        .collect::<Vec<_>>();
+    // This quote block names and describes the calls of this pallet. The runtime aggregates the
+    // descriptions into its metadata, and uses the names to identify calls, like to pause them.
+    let metadata_impl =
+        quote! {
+		#[allow(deprecated)]
+		impl #impl_generics Call #ty_generics #where_clause {
+			// The name of the function called.
+			pub fn name(&self) -> &'static str {
+				match self {
+					#(
+						#( #fn_cfg )*
+						Call::#fn_name { .. } => stringify!(#fn_name),
+					)*
+					#ignore_arm
+				}
+			}
+
+			// The name of the pallet, which is the name of its module. Unlike the name of the
+			// pallet in the runtime, this is the same for every instance of the pallet.
+			pub fn pallet_name(&self) -> &'static str {
+				module_path!().rsplit("::").next().unwrap_or_default()
+			}
+
+			pub fn metadata() -> Vec<crate::metadata::CallMetadata> {
+				// Functions which are compiled out are not described.
+				#[allow(unused_mut)]
+				let mut calls = Vec::new();
+				#(
+					#( #fn_cfg )*
+					calls.push(crate::metadata::CallMetadata {
+						name: stringify!(#fn_name),
+						index: #fn_index,
+						args: vec![
+							#(
+								crate::metadata::ArgMetadata {
+									name: stringify!(#args_name),
+									ty: core::any::type_name::<#args_type>(),
+								}
+							),*
+						],
+						docs: vec![ #( #fn_docs ),* ],
+						deprecated: #fn_deprecated,
+					});
+				)*
+				calls
+			}
+		}
+	};
+
+    // This quote block encodes calls as the index of their function followed by their arguments,
+    // and decodes them back. Like `Clone` and `Debug`, this only requires the arguments to be
+    // encodable or decodable.
+    let encode_impl =
+        quote! {
+		#[allow(deprecated)]
+		impl #impl_generics crate::storage::Encode for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: crate::storage::Encode, )* )*
+		{
+			// The output is named so that it cannot clash with the name of an argument.
+			fn encode_to(&self, __dest: &mut Vec<u8>) {
+				match self {
+					#(
+						#( #fn_cfg )*
+						Call::#fn_name { #( #args_name ),* } => {
+							__dest.push(#fn_index);
+							#( crate::storage::Encode::encode_to(#args_name, __dest); )*
+						},
+					)*
+					#ignore_arm
+				}
+			}
+		}
+
+		#[allow(deprecated)]
+		impl #impl_generics crate::storage::Decode for Call #ty_generics
+		where
+			#( #where_predicates, )*
+			#( #( #args_type: crate::storage::Decode, )* )*
+		{
+			// The arguments are decoded in order, since fields are evaluated in the order written.
+			fn decode(__input: &mut &[u8]) -> Option<Self> {
+				match <u8 as crate::storage::Decode>::decode(__input)? {
+					#(
+						#( #fn_cfg )*
+						#fn_index => Some(Call::#fn_name {
+							#( #args_name: crate::storage::Decode::decode(__input)?, )*
+						}),
+					)*
+					_ => None,
+				}
+			}
+		}
+	};
+
     // Return the generated code.
-    dispatch_impl.into()
+    quote! {
+		#( #duplicate_index_checks )*
+		#dispatch_impl
+		#constructors_impl
+		#traits_impl
+		#encode_impl
+		#metadata_impl
+	}
+}
+
+/// Whether `tokens` mention `ident`, for example whether a type uses a type parameter.
+fn mentions_ident(tokens: proc_macro2::TokenStream, ident: &syn::Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(other) => other == *ident,
+        proc_macro2::TokenTree::Group(group) => mentions_ident(group.stream(), ident),
+        _ => false,
+    })
 }
@@ -1,15 +1,13 @@
 pub mod expand;
 pub mod parse;
 
+use quote::ToTokens;
+
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn call(
     _attr: proc_macro::TokenStream,
     item: proc_macro::TokenStream
 ) -> proc_macro::TokenStream {
-    // The final expanded code will be placed here.
-    // Since our macro only adds new code, our final product will contain all of our old code too,
-    // hence we clone `item`.
-    let mut finished = item.clone();
     let item_mod = syn::parse_macro_input!(item as syn::Item);
 
     // First we parse the call functions implemented for the pallet...
@@ -19,7 +17,21 @@ pub fn call(
         Err(e) => e.to_compile_error().into(),
     };
 
+    // Since our macro only adds new code, our final product will contain all of our old code too,
+    // except for the `#[call_index]` attributes, which the compiler does not know.
+    let mut finished: proc_macro::TokenStream = match item_mod {
+        syn::Item::Impl(mut item_impl) => {
+            for item in item_impl.items.iter_mut() {
+                if let syn::ImplItem::Fn(method) = item {
+                    method.attrs.retain(|attr| !parse::is_call_index_attr(attr));
+                }
+            }
+            item_impl.into_token_stream().into()
+        }
+        item => item.into_token_stream().into(),
+    };
+
     // Add our generated code to the end, and return the final result.
     finished.extend(generated);
-    return finished;
+    finished
 }
@@ -11,9 +11,11 @@ mod keyword {
 /// functions.
 #[derive(Debug)]
 pub struct CallDef {
-    /// This is the name of the pallet struct where the callable functions are implemented. We
-    /// mostly assume it is `Pallet`.
-    pub pallet_struct: syn::Ident,
+    /// This is the type of the pallet struct where the callable functions are implemented, as
+    /// written in the `impl`, like `Pallet<T>` or `crate::balances::Pallet<T, I>`.
+    pub pallet_type: Box<syn::Type>,
+    /// The generics of the `impl`, including its where-clause, which `Call` is generic over too.
+    pub generics: syn::Generics,
     /// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
     pub methods: Vec<CallVariantDef>,
 }
@@ -23,8 +25,46 @@ pub struct CallDef {
 pub struct CallVariantDef {
     /// The function name.
     pub name: syn::Ident,
+    /// The index of the function in its pallet, from its `#[call_index(n)]` attribute.
+    pub index: u8,
+    /// What the function has access to, based on its first argument. See `CallReceiver`.
+    pub receiver: CallReceiver,
+    /// Who the function can be called by, based on its second argument. See `CallCaller`.
+    pub caller: CallCaller,
     /// Information on args of the function: `(name, type)`.
     pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+    /// The lines of the doc comments of the function, for the metadata.
+    pub docs: Vec<String>,
+    /// The doc comments and `#[deprecated]` attribute of the function, which are copied to its
+    /// variant of `Call` and its constructor.
+    pub variant_attrs: Vec<syn::Attribute>,
+    /// The `#[cfg]` attributes of the function, which are copied to everything generated for it, so
+    /// that a function which is compiled out is not part of `Call` either.
+    pub cfg_attrs: Vec<syn::Attribute>,
+    /// The note of the `#[deprecated]` attribute of the function, or an empty note when it has
+    /// none. This is `None` when the function is not deprecated.
+    pub deprecated: Option<String>,
+}
+
+/// The first argument of a callable function, which decides what state the function can access.
+#[derive(Debug)]
+pub enum CallReceiver {
+    /// Some variant of `self`. The function only has access to the storage of its own pallet.
+    Pallet,
+    /// `runtime: &mut T`. The function has access to the whole runtime, so it can reach other
+    /// pallets or dispatch further calls. This requires `T` to be the runtime including the pallet.
+    Runtime,
+}
+
+/// The second argument of a callable function, which decides who can call the function.
+#[derive(Debug)]
+pub enum CallCaller {
+    /// `caller: T::AccountId`. The function can only be called by a signed origin, and is given its
+    /// account.
+    Signed,
+    /// `origin: Origin<T::AccountId>`. The function can be called by any origin, and checks the
+    /// origin itself, for example to only allow root.
+    Origin,
 }
 
 impl CallDef {
@@ -36,12 +76,19 @@ impl CallDef {
             return Err(syn::Error::new(item.span(), "Invalid pallet::call, expected item impl"));
         };
 
-        // Extract the name of the struct. We mostly assume it is `Pallet`, but we can handle it
-        // when it isn't.
-        let pallet_struct = match &*item_impl.self_ty {
-            syn::Type::Path(tp) => tp.path.segments.first().unwrap().ident.clone(),
-            _ => panic!("not supported tokens"),
-        };
+        // The functions must be implemented on the pallet struct itself, not for a trait.
+        if let Some((_, path, _)) = &item_impl.trait_ {
+            let msg = "Invalid pallet::call, expected an impl without a trait";
+            return Err(syn::Error::new(path.span(), msg));
+        }
+
+        // Check the struct is a path, like `Pallet<T>`. We mostly assume it is `Pallet`, but we
+        // can handle it when it isn't, or when it is a longer path like `crate::balances::Pallet`.
+        let pallet_type = item_impl.self_ty.clone();
+        if !matches!(&*pallet_type, syn::Type::Path(tp) if tp.qself.is_none()) {
+            let msg = "Invalid pallet::call, expected the pallet struct, like `Pallet<T>`";
+            return Err(syn::Error::new(pallet_type.span(), msg));
+        }
 
         // Here is where we will store all the callable functions.
         let mut methods = vec![];
@@ -50,37 +97,66 @@ impl CallDef {
                 // Here is where we will store all the args for each callable functions.
                 let mut args = vec![];
 
-                // First argument should be some variant of `self`.
-                match method.sig.inputs.first() {
-                    Some(syn::FnArg::Receiver(_)) => {}
+                // First argument should be some variant of `self`, or `runtime: &mut T`.
+                let receiver = match method.sig.inputs.first() {
+                    Some(syn::FnArg::Receiver(_)) => CallReceiver::Pallet,
+                    Some(syn::FnArg::Typed(arg)) => {
+                        check_runtime_arg(arg)?;
+                        CallReceiver::Runtime
+                    }
                     _ => {
-                        let msg = "Invalid call, first argument must be a variant of self";
+                        let msg = "Invalid call, first argument must be self or `runtime: &mut T`";
                         return Err(syn::Error::new(method.sig.span(), msg));
                     }
-                }
+                };
 
-                // The second argument should be the `caller: T::AccountId` argument.
-                match method.sig.inputs.iter().skip(1).next() {
+                // The second argument should be the `caller: T::AccountId` argument, or the
+                // `origin: Origin<T::AccountId>` argument.
+                let caller = match method.sig.inputs.iter().nth(1) {
+                    Some(syn::FnArg::Typed(arg)) if is_origin_arg(arg) => {
+                        // Here we specifically check that this argument is as we expect for
+                        // `origin: Origin<T::AccountId>`.
+                        check_origin_arg(arg)?;
+                        CallCaller::Origin
+                    }
                     Some(syn::FnArg::Typed(arg)) => {
                         // Here we specifically check that this argument is as we expect for
                         // `caller: T::AccountId`.
                         check_caller_arg(arg)?;
+                        CallCaller::Signed
                     }
                     _ => {
                         let msg = "Invalid call, second argument should be `caller: T::AccountId`";
                         return Err(syn::Error::new(method.sig.span(), msg));
                     }
-                }
+                };
 
                 let fn_name = method.sig.ident.clone();
 
-                // Parsing the rest of the args. Skipping 2 for `self` and `caller`.
+                // Every callable function must have an explicit index, so that reordering the
+                // functions does not change how calls are encoded. Functions with `#[cfg]`
+                // attributes may share an index with another function which is never compiled
+                // in at the same time, so those are checked in the generated code instead.
+                let index = get_call_index(&method)?;
+                let has_cfg = method.attrs.iter().any(|attr| attr.path().is_ident("cfg"));
+                let same_index = methods
+                    .iter()
+                    .filter(|other: &&CallVariantDef| other.cfg_attrs.is_empty() && !has_cfg)
+                    .find(|other| other.index == index);
+                if let Some(other) = same_index {
+                    let msg = format!("Duplicate call index {}, used by `{}`", index, other.name);
+                    return Err(syn::Error::new(method.sig.ident.span(), msg));
+                }
+
+                // Parsing the rest of the args. Skipping 2 for `self` (or `runtime`) and `caller`
+                // (or `origin`).
                 for arg in method.sig.inputs.iter().skip(2) {
                     // All arguments should be typed.
                     let arg = if let syn::FnArg::Typed(arg) = arg {
                         arg
                     } else {
-                        unreachable!("All args should be typed.");
+                        let msg = "Invalid pallet::call, only the first argument can be self";
+                        return Err(syn::Error::new(arg.span(), msg));
                     };
 
                     // Extract the name of the argument.
@@ -95,30 +171,180 @@ impl CallDef {
                     args.push((arg_ident, arg.ty.clone()));
                 }
 
-                // Store all the function name and the arg data for the function.
-                methods.push(CallVariantDef { name: fn_name, args });
+                // Store all the function name and the arg data for the function, with the
+                // attributes we keep.
+                let docs = get_doc_literals(&method.attrs);
+                let variant_attrs = method.attrs
+                    .iter()
+                    .filter(|attr| {
+                        attr.path().is_ident("doc") || attr.path().is_ident("deprecated")
+                    })
+                    .cloned()
+                    .collect();
+                let cfg_attrs = method.attrs
+                    .iter()
+                    .filter(|attr| attr.path().is_ident("cfg"))
+                    .cloned()
+                    .collect();
+                let deprecated = get_deprecation(&method.attrs)?;
+                methods.push(CallVariantDef {
+                    name: fn_name,
+                    index,
+                    receiver,
+                    caller,
+                    args,
+                    docs,
+                    variant_attrs,
+                    cfg_attrs,
+                    deprecated,
+                });
             }
         }
 
         // Return all callable functions for this pallet.
-        Ok(Self { pallet_struct, methods })
+        Ok(Self { pallet_type, generics: item_impl.generics, methods })
     }
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
+/// Get the index of a callable function from its `#[call_index(n)]` attribute.
+fn get_call_index(method: &syn::ImplItemFn) -> syn::Result<u8> {
+    let mut attrs = method.attrs.iter().filter(|attr| is_call_index_attr(attr));
+    let Some(attr) = attrs.next() else {
+        let msg = "Invalid call, missing `#[call_index(n)]` attribute";
+        return Err(syn::Error::new(method.sig.ident.span(), msg));
+    };
+    if let Some(other) = attrs.next() {
+        return Err(syn::Error::new(other.span(), "Invalid call, duplicate `#[call_index]`"));
+    }
+    attr.parse_args::<syn::LitInt>()?.base10_parse::<u8>()
+}
+
+/// Get the note of the `#[deprecated]` attribute of a callable function, written as
+/// `#[deprecated]`, `#[deprecated = "note"]` or `#[deprecated(since = "..", note = "note")]`.
+fn get_deprecation(attrs: &[syn::Attribute]) -> syn::Result<Option<String>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("deprecated")) else {
+        return Ok(None);
+    };
+    let note = match &attr.meta {
+        syn::Meta::Path(_) => String::new(),
+        syn::Meta::NameValue(meta) => {
+            let syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) = &meta.value else {
+                let msg = "Invalid call, expected the note of `#[deprecated]` to be a string";
+                return Err(syn::Error::new(meta.value.span(), msg));
+            };
+            lit.value()
+        }
+        syn::Meta::List(list) => {
+            let mut note = String::new();
+            list.parse_nested_meta(|meta| {
+                let value = meta.value()?.parse::<syn::LitStr>()?;
+                if meta.path.is_ident("note") {
+                    note = value.value();
+                }
+                Ok(())
+            })?;
+            note
+        }
+    };
+    Ok(Some(note))
+}
+
+/// Whether this is the `#[call_index(n)]` attribute, which gives the index of a callable function.
+pub fn is_call_index_attr(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("call_index")
+}
+
+/// Check runtime arg is exactly: `runtime: &mut T`.
 ///
-/// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
-    pub struct CheckDispatchableFirstArg;
-    impl syn::parse::Parse for CheckDispatchableFirstArg {
+/// Like `check_caller_arg`, this is kept strict to keep the code simple.
+pub fn check_runtime_arg(arg: &syn::PatType) -> syn::Result<()> {
+    pub struct CheckDispatchableRuntimeArg;
+    impl syn::parse::Parse for CheckDispatchableRuntimeArg {
         fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+            input.parse::<syn::Token![&]>()?;
+            input.parse::<syn::Token![mut]>()?;
             input.parse::<keyword::T>()?;
-            input.parse::<syn::Token![::]>()?;
-            input.parse::<keyword::AccountId>()?;
             Ok(Self)
         }
     }
 
+    // This checks the arg name is `runtime` or `_runtime`.
+    if let syn::Pat::Ident(ident) = &*arg.pat {
+        if &ident.ident != "runtime" && &ident.ident != "_runtime" {
+            let msg = "Invalid name for first parameter: expected `runtime: &mut T`";
+            return Err(syn::Error::new(ident.span(), msg));
+        }
+    }
+
+    // This checks the type is `&mut T` with `CheckDispatchableRuntimeArg`
+    let ty = &arg.ty;
+    syn::parse2::<CheckDispatchableRuntimeArg>(ty.to_token_stream()).map_err(|e| {
+        let msg = "Invalid type for first parameter: expected `runtime: &mut T`";
+        let mut err = syn::Error::new(ty.span(), msg);
+        err.combine(e);
+        err
+    })?;
+
+    Ok(())
+}
+
+/// Whether the arg is named `origin` or `_origin`, in which case it should be checked with
+/// `check_origin_arg` rather than `check_caller_arg`.
+fn is_origin_arg(arg: &syn::PatType) -> bool {
+    matches!(&*arg.pat, syn::Pat::Ident(pat) if pat.ident == "origin" || pat.ident == "_origin")
+}
+
+/// Check origin arg is: `origin: Origin<T::AccountId>`, where `Origin` can be a full path like
+/// `crate::support::Origin`.
+pub fn check_origin_arg(arg: &syn::PatType) -> syn::Result<()> {
+    let msg = "Invalid type for second parameter: expected `origin: Origin<T::AccountId>`";
+    let err = || syn::Error::new(arg.ty.span(), msg);
+
+    // This checks the last segment of the type is `Origin<..>`.
+    let syn::Type::Path(type_path) = &*arg.ty else {
+        return Err(err());
+    };
+    let segment = type_path.path.segments.last().ok_or_else(err)?;
+    if segment.ident != "Origin" {
+        return Err(err());
+    }
+
+    // This checks the generic argument of `Origin` is `T::AccountId`.
+    let syn::PathArguments::AngleBracketed(generics) = &segment.arguments else {
+        return Err(err());
+    };
+    match generics.args.first() {
+        Some(syn::GenericArgument::Type(ty)) if generics.args.len() == 1 => {
+            syn::parse2::<CheckDispatchableAccountId>(ty.to_token_stream()).map_err(|e| {
+                let mut err = err();
+                err.combine(e);
+                err
+            })?;
+        }
+        _ => {
+            return Err(err());
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses exactly `T::AccountId`.
+struct CheckDispatchableAccountId;
+
+impl syn::parse::Parse for CheckDispatchableAccountId {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        input.parse::<keyword::T>()?;
+        input.parse::<syn::Token![::]>()?;
+        input.parse::<keyword::AccountId>()?;
+        Ok(Self)
+    }
+}
+
+/// Check caller arg is exactly: `caller: T::AccountId`.
+///
+/// This is kept strict to keep the code simple.
+pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
     // This checks the arg name is `caller` or `_caller`.
     if let syn::Pat::Ident(ident) = &*arg.pat {
         // We also support the name as `_caller` for when the variable is unused.
@@ -128,9 +354,9 @@ pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
         }
     }
 
-    // This checks the type is `T::AccountId` with `CheckDispatchableFirstArg`
+    // This checks the type is `T::AccountId` with `CheckDispatchableAccountId`
     let ty = &arg.ty;
-    syn::parse2::<CheckDispatchableFirstArg>(ty.to_token_stream()).map_err(|e| {
+    syn::parse2::<CheckDispatchableAccountId>(ty.to_token_stream()).map_err(|e| {
         let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
         let mut err = syn::Error::new(ty.span(), msg);
         err.combine(e);
@@ -139,3 +365,25 @@ pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
 
     Ok(())
 }
+
+/// The lines of the doc comments in `attrs`, without the space following `///`.
+pub fn get_doc_literals(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| {
+            let syn::Meta::NameValue(meta) = &attr.meta else {
+                return None;
+            };
+            if !meta.path.is_ident("doc") {
+                return None;
+            }
+            match &meta.value {
+                syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) => {
+                    let doc = lit.value();
+                    Some(doc.strip_prefix(' ').unwrap_or(&doc).to_string())
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
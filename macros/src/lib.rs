@@ -1,6 +1,44 @@
 mod call;
 mod runtime;
+mod storage;
 
+/// Expand the callable functions of a pallet.
+///
+/// This generates an `enum Call` with a variant for each function in the `impl` block, and a
+/// `Call::dispatch` function which routes a call to the matching function on behalf of an `origin`.
+///
+/// Each function also gets a constructor on `Call`, named after it with a `new_` prefix and taking
+/// its arguments in order, like `Call::new_transfer(to, amount)`.
+///
+/// `Call::metadata` describes each function: its name, index, arguments and doc comments, and
+/// `Call::name` and `Call::pallet_name` name a call, for example for logging. `Call` implements
+/// `Clone`, `Debug`, `PartialEq` and `Eq` when the arguments of every function do, without
+/// requiring them from `T`.
+///
+/// The doc comments and `#[deprecated]` attribute of each function are copied to its variant of
+/// `Call` and to its constructor, and are described in `Call::metadata`. Functions with `#[cfg]`
+/// attributes have them copied to everything generated for them, so a function which is compiled
+/// out cannot be called, dispatched or described.
+///
+/// The `impl` can be on any path to the pallet struct, like `Pallet<T>` or
+/// `crate::balances::Pallet<T, I>`. `Call` has the same generics and where-clause as the `impl`,
+/// so pallets can be generic over an instance `I` as well as `T`. Like on the pallet struct, the
+/// instance of `Call` defaults to `()`.
+///
+/// Each function must be given an index with `#[call_index(n)]`, which is unique in the pallet.
+/// Calls are encoded with `storage::Encode` as this index followed by their arguments, so the
+/// encoding of calls does not change when functions are reordered, and decoded back with
+/// `storage::Decode`, so calls can be kept in storage.
+///
+/// The first argument of each function decides what it has access to:
+/// - `&mut self` - the function only accesses the storage of its own pallet.
+/// - `runtime: &mut T` - the function is given the whole runtime, so it can access other pallets or
+///   dispatch further calls. The pallet must then require the access it needs in its `Config`.
+///
+/// The second argument decides who can call the function:
+/// - `caller: T::AccountId` - the origin must be signed, and the function is given its account.
+/// - `origin: Origin<T::AccountId>` - the function is given the origin as is, for example to only
+///   allow root with `origin.ensure_root()`.
 #[proc_macro_attribute]
 pub fn call(
     attr: proc_macro::TokenStream,
@@ -9,22 +47,86 @@ pub fn call(
     call::call(attr, item)
 }
 
+/// Expand the storage of a pallet.
+///
+/// This is placed on the `Pallet` struct. Fields whose type is a `storage::StorageValue`,
+/// `storage::StorageMap` or `storage::StorageDoubleMap` are storage items, kept in the key-value
+/// store shared by all the pallets of the runtime. Their keys are prefixed by the name of the
+/// module of the pallet and the name of the field, and the keys of maps are hashed.
+///
+/// When the pallet struct has a second type parameter, like `Pallet<T, I>`, this is its instance,
+/// which implements `support::Instance`. The prefix of the instance is added to the prefix of the
+/// pallet, so each instance has its own storage.
+///
+/// This generates `fn new(store: &storage::Store)` on the pallet, which creates each storage item
+/// in `store`. Other fields start from their `Default`. This also implements
+/// `metadata::PalletStorageMetadata`, describing each storage item.
+#[proc_macro_attribute]
+pub fn storage(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream
+) -> proc_macro::TokenStream {
+    storage::storage(attr, item)
+}
+
 /// Expand the `Runtime` definition.
 ///
+/// The runtime struct can have any name, and must include the system pallet, which is the field
+/// marked with `#[runtime::system]`, or else the field named `system`. The system pallet can be at
+/// any position and in any module, since its `Config` is found from its type: `Config` in the
+/// same module as its `Pallet`.
+///
 /// This generates function implementations on `Runtime`:
 /// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
-///   included in the runtime.
+///   included in the runtime. All the pallets share a new `storage::Store`, kept in memory, which
+///   is passed to the `fn new(store)` of each pallet.
+/// - `fn with_store()` - which does the same with a given `storage::Store`, for example on disk.
+///   A runtime created from a store it committed to before resumes from its last block.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
 ///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   valid block number. It also calls the `support::Hooks` of system, then of every other pallet
+///   in the order they are declared: `on_initialize` before the extrinsics, then `on_idle` with
+///   the weight left in the block, and finally `on_finalize`. The store is committed after each
+///   block. An invalid block changes nothing: the structure of the block is checked before it is
+///   executed, and the changes to the store are reverted when one of its inherents fails.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
-/// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
+///   all pallets, including system.
 /// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   logic like incrementing the nonce of the user is included in the generated code. Extrinsics
+///   are dispatched with a signed origin of their caller. Unsigned extrinsics must be inherents,
+///   and are dispatched with no origin: they must come before signed extrinsics, and the block is
+///   invalid if one of them fails. Calls which the `BaseCallFilter` of system does not contain
+///   fail, unless they are dispatched by root or are inherents.
+/// - implements `support::GetCallName` on `RuntimeCall`, naming the pallet and function called,
+///   as well as `Clone`, `Debug`, `PartialEq` and `Eq`, forwarding to the `Call` of each pallet.
+/// - implements `From` the `Call` of each pallet for `RuntimeCall`, so that the call of a pallet
+///   can be turned into a `RuntimeCall` with `.into()`, like by `support::Extrinsic::signed`.
+///
+/// The same pallet can be included several times with different instances, like
+/// `credits: balances::Pallet<Self, Instance2>`. The variant of `RuntimeCall` is named after the
+/// field, and holds the `Call` of the pallet for that instance.
+///
+/// Pallets can be given an index with `#[pallet_index(n)]`, which is unique in the runtime, or are
+/// given the lowest index which no other pallet uses. System always has the index 0, which no
+/// other pallet can use. A `RuntimeCall` is encoded as the index of its pallet followed by the
+/// encoded call of the pallet.
+///
+/// Pallets marked with `#[inherent]` implement `support::ProvideInherent`. For those, this also
+/// generates:
+/// - `fn create_inherents()` - which creates the inherents of a new block from `InherentData`.
+/// - `fn check_inherents()` - which checks the inherents of a block against our `InherentData`.
+/// - `execute_block()` rejects blocks missing an inherent which `is_inherent_required`.
+///
+/// `fn metadata()` aggregates the calls described by `#[macros::call]` and the storage items
+/// described by `#[macros::storage]` of every pallet into a `metadata::RuntimeMetadata`, which can
+/// be serialized to JSON, along with the constants and events of the pallets which implement
+/// `metadata::PalletConstantMetadata` and `metadata::PalletEventMetadata`. Pallets without storage
+/// items, constants or events are described with none, without implementing those traits.
+///
+/// Finally, this implements `AsRef` and `AsMut` on `Runtime` for every pallet, including system, so
+/// that pallets can access each other through their `Config`.
 #[proc_macro_attribute]
 pub fn runtime(
     attr: proc_macro::TokenStream,
@@ -3,7 +3,16 @@ use quote::quote;
 
 /// See the `fn runtime` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
-    let RuntimeDef { runtime_struct, pallets } = def;
+    let RuntimeDef {
+        runtime_struct,
+        system: (system_name, system_type),
+        system_config,
+        system_call,
+        pallets,
+        pallet_calls,
+        pallet_indices,
+        inherents,
+    } = def;
 
     // This is a vector of all the pallet names, not including system.
     let pallet_names = pallets
@@ -16,38 +25,245 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
         .map(|(_, type_)| type_.clone())
         .collect::<Vec<_>>();
 
+    // These are the names, call types and indices of all the callable pallets, which is all the
+    // pallets including system, with system first.
+    let call_names = std::iter::once(system_name.clone())
+        .chain(pallet_names.iter().cloned())
+        .collect::<Vec<_>>();
+    let call_types = std::iter::once(system_call.clone())
+        .chain(pallet_calls.iter().cloned())
+        .collect::<Vec<_>>();
+    let call_indices = std::iter::once(0u8)
+        .chain(pallet_indices.iter().copied())
+        .collect::<Vec<_>>();
+
+    // These are the names and types of the pallets which provide inherents.
+    let inherent_names = inherents
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    let inherent_types = inherents
+        .iter()
+        .map(|(_, type_)| type_.clone())
+        .collect::<Vec<_>>();
+
+    // This is a vector of all the pallet types which have hooks called in each block, which is all
+    // the pallets including system, in the order they are declared.
+    let hooks_pallet_types = std::iter::once(system_type.clone())
+        .chain(pallet_types.iter().cloned())
+        .collect::<Vec<syn::Type>>();
+
     // This quote block implements functions on the `Runtime` struct.
     let runtime_impl =
         quote! {
 		impl #runtime_struct {
-			// Create a new instance of the main Runtime, by creating a new instance of each pallet.
+			// Create a new instance of the main Runtime, with its state kept in memory.
 			fn new() -> Self {
+				Self::with_store(crate::storage::Store::new())
+			}
+
+			// Create a new instance of the main Runtime, by creating a new instance of each pallet.
+			// The storage items of all the pallets are kept in `store`, so a runtime created from a
+			// store which was committed to before resumes from its state.
+			fn with_store(store: crate::storage::Store) -> Self {
 				Self {
 					// Since system is not included in the list of pallets, we manually add it here.
-					system: <system::Pallet::<Self>>::new(),
+					#system_name: <#system_type>::new(&store),
 					#(
-						#pallet_names: <#pallet_types>::new()
+						#pallet_names: <#pallet_types>::new(&store)
 					),*
 				}
 			}
 
-			// Execute a block of extrinsics. Increments the block number.
+			// Execute a block of extrinsics. Increments the block number, and calls the hooks of
+			// every pallet around the extrinsics. An invalid block changes nothing: its structure
+			// is checked before any state changes, and the changes made before a failed inherent
+			// are reverted.
 			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
-				self.system.inc_block_number();
-				if block.header.block_number != self.system.block_number() {
+				Self::check_block(&block, self.#system_name.block_number())?;
+				let result = self.apply_block(block);
+				if result.is_err() {
+					self.#system_name.storage().revert();
+				}
+				result
+			}
+
+			// Check the structure of a block following `parent`: its block number, that it includes
+			// the inherents every block must have, and that its unsigned extrinsics are inherents
+			// placed before its signed extrinsics.
+			fn check_block(
+				block: &types::Block,
+				mut parent: <Self as #system_config>::BlockNumber,
+			) -> crate::support::DispatchResult {
+				parent += <<Self as #system_config>::BlockNumber as num::traits::One>::one();
+				if block.header.block_number != parent {
 					return Err(&"block number does not match what is expected")
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
+				#(
+					let included = block.extrinsics.iter().any(|ext| {
+						ext.caller.is_none() && matches!(
+							&ext.call,
+							RuntimeCall::#inherent_names(call)
+								if <#inherent_types as crate::support::ProvideInherent>::is_inherent(call)
 						)
 					});
+					if <#inherent_types as crate::support::ProvideInherent>::is_inherent_required() && !included {
+						return Err(&"a required inherent is missing")
+					}
+				)*
+				// Unsigned extrinsics are inherents provided by the block author, so they must come
+				// first.
+				let mut signed_seen = false;
+				for ext in block.extrinsics.iter() {
+					match &ext.caller {
+						None if signed_seen => {
+							return Err(&"unsigned extrinsics must come before signed extrinsics")
+						}
+						None if !Self::is_inherent(&ext.call) => {
+							return Err(&"unsigned extrinsic is not an inherent")
+						}
+						None => {}
+						Some(_) => signed_seen = true,
+					}
+				}
+				Ok(())
+			}
+
+			// Execute a block checked by `check_block`. The block is invalid if one of its
+			// inherents fails.
+			fn apply_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+				self.#system_name.inc_block_number();
+				// Let each pallet do its work for this block, before any extrinsic is dispatched.
+				#(
+					let weight = <#hooks_pallet_types as crate::support::Hooks<Self>>::on_initialize(
+						self,
+						block.header.block_number,
+					);
+					self.#system_name.register_weight(weight);
+				)*
+				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
+					self.#system_name.register_weight(<Self as #system_config>::EXTRINSIC_WEIGHT);
+					match caller {
+						None => {
+							self.dispatch(crate::support::Origin::None, call)?;
+						}
+						Some(caller) => {
+							self.#system_name.inc_nonce(&caller);
+							let _res = self.dispatch(crate::support::Origin::Signed(caller), call).map_err(|e| {
+								eprintln!(
+									"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
+									block.header.block_number, i, e
+								)
+							});
+						}
+					}
+				}
+				// Let each pallet use the weight left in this block, then finish the block.
+				#(
+					let remaining_weight = self.#system_name.remaining_weight();
+					let weight = <#hooks_pallet_types as crate::support::Hooks<Self>>::on_idle(
+						self,
+						remaining_weight,
+					);
+					self.#system_name.register_weight(weight);
+				)*
+				#(
+					<#hooks_pallet_types as crate::support::Hooks<Self>>::on_finalize(
+						self,
+						block.header.block_number,
+					);
+				)*
+				// Make the state after this block durable, so the runtime can resume from it.
+				self.#system_name.storage().commit().map_err(|_| "failed to commit the block")?;
+				Ok(())
+			}
+
+			// Create the inherents for a new block from `data`, to be placed before its signed
+			// extrinsics.
+			fn create_inherents(data: &crate::support::InherentData) -> Vec<types::Extrinsic> {
+				let mut inherents = Vec::new();
+				#(
+					let inherent = <#inherent_types as crate::support::ProvideInherent>::create_inherent(data);
+					if let Some(call) = inherent {
+						inherents.push(support::Extrinsic {
+							caller: None,
+							call: RuntimeCall::#inherent_names(call),
+						});
+					}
+				)*
+				inherents
+			}
+
+			// Check the inherents of a block against our own `data`, before executing it.
+			fn check_inherents(
+				block: &types::Block,
+				data: &crate::support::InherentData,
+			) -> crate::support::DispatchResult {
+				for ext in block.extrinsics.iter().filter(|ext| ext.caller.is_none()) {
+					match &ext.call {
+						#(
+							RuntimeCall::#inherent_names(call)
+								if <#inherent_types as crate::support::ProvideInherent>::is_inherent(call) =>
+							{
+								<#inherent_types as crate::support::ProvideInherent>::check_inherent(call, data)?;
+							}
+						)*
+						_ => {}
+					}
 				}
 				Ok(())
 			}
+
+			// Describe the pallets of this runtime, with their index, calls, storage, constants and
+			// events.
+			pub fn metadata() -> crate::metadata::RuntimeMetadata {
+				// See `metadata::MetadataOf` for how pallets without storage, constants or events
+				// are described.
+				use crate::metadata::{
+					DescribeConstants, DescribeEvents, DescribeNoConstants, DescribeNoEvents,
+					DescribeNoStorage, DescribeStorage, MetadataOf,
+				};
+				crate::metadata::RuntimeMetadata {
+					pallets: vec![
+						crate::metadata::PalletMetadata {
+							name: stringify!(#system_name),
+							index: 0,
+							calls: <#system_call>::metadata(),
+							storage: (&MetadataOf::<#system_type>(core::marker::PhantomData))
+								.storage_metadata(),
+							constants: (&MetadataOf::<#system_type>(core::marker::PhantomData))
+								.constant_metadata(),
+							events: (&MetadataOf::<#system_type>(core::marker::PhantomData))
+								.event_metadata(),
+						},
+						#(
+							crate::metadata::PalletMetadata {
+								name: stringify!(#pallet_names),
+								index: #pallet_indices,
+								calls: <#pallet_calls>::metadata(),
+								storage: (&MetadataOf::<#pallet_types>(core::marker::PhantomData))
+									.storage_metadata(),
+								constants: (&MetadataOf::<#pallet_types>(core::marker::PhantomData))
+									.constant_metadata(),
+								events: (&MetadataOf::<#pallet_types>(core::marker::PhantomData))
+									.event_metadata(),
+							}
+						),*
+					],
+				}
+			}
+
+			// Whether `call` is the inherent of a pallet marked with `#[inherent]`.
+			#[allow(unreachable_patterns)]
+			fn is_inherent(call: &RuntimeCall) -> bool {
+				match call {
+					#(
+						RuntimeCall::#inherent_names(call) =>
+							<#inherent_types as crate::support::ProvideInherent>::is_inherent(call),
+					)*
+					_ => false,
+				}
+			}
 		}
 	};
 
@@ -60,28 +276,44 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
 		pub enum RuntimeCall {
-			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
+			#( #call_names(#call_types) ),*
 		}
 
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+			type Caller = crate::support::Origin<<#runtime_struct as #system_config>::AccountId>;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call on behalf of an origin.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
-			// Note that we extract the `caller` from the extrinsic, and use that information
-			// to determine who we are executing the call on behalf of.
+			// Note that for extrinsics, the origin is the signed `caller` of the extrinsic, and we use
+			// that information to determine who we are executing the call on behalf of.
 			fn dispatch(
 				&mut self,
-				caller: Self::Caller,
+				origin: Self::Caller,
 				runtime_call: Self::Call,
 			) -> crate::support::DispatchResult {
+				// Only root can dispatch calls which the `BaseCallFilter` of system does not contain.
+				// Inherents are not filtered either, since every block needs them.
+				let allowed = <
+					<#runtime_struct as #system_config>::BaseCallFilter as crate::support::Contains<
+						#runtime_struct,
+						RuntimeCall,
+					>
+				>::contains(self, &runtime_call);
+				let exempt = match origin {
+					crate::support::Origin::Root => true,
+					crate::support::Origin::None => Self::is_inherent(&runtime_call),
+					crate::support::Origin::Signed(_) => false,
+				};
+				if !allowed && !exempt {
+					return Err(&"call is not allowed by the base call filter")
+				}
 				// This match statement will allow us to correctly route `RuntimeCall`s
 				// to the appropriate pallet level call.
 				match runtime_call {
 					#(
-						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+						RuntimeCall::#call_names(call) => {
+							call.dispatch(self, origin)?;
 						}
 					),*
 				}
@@ -90,9 +322,143 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		}
 	};
 
+    // This quote block encodes a `RuntimeCall` as the index of its pallet followed by the encoded
+    // call of the pallet, and decodes it back.
+    let encode_impl =
+        quote! {
+		impl crate::storage::Encode for RuntimeCall {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						RuntimeCall::#call_names(call) => {
+							dest.push(#call_indices);
+							crate::storage::Encode::encode_to(call, dest);
+						}
+					),*
+				}
+			}
+		}
+
+		impl crate::storage::Decode for RuntimeCall {
+			fn decode(input: &mut &[u8]) -> Option<Self> {
+				match <u8 as crate::storage::Decode>::decode(input)? {
+					#(
+						#call_indices => {
+							Some(RuntimeCall::#call_names(crate::storage::Decode::decode(input)?))
+						}
+					)*
+					_ => None,
+				}
+			}
+		}
+	};
+
+    // This quote block implements `Clone`, `GetCallName`, `Debug`, `PartialEq` and `Eq` for
+    // `RuntimeCall`, forwarding to the `Call` of each pallet, and converts the `Call` of each
+    // pallet into a `RuntimeCall`.
+    let traits_impl =
+        quote! {
+		impl Clone for RuntimeCall {
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						RuntimeCall::#call_names(call) => RuntimeCall::#call_names(call.clone())
+					),*
+				}
+			}
+		}
+
+		#(
+			impl From<#call_types> for RuntimeCall {
+				fn from(call: #call_types) -> Self {
+					RuntimeCall::#call_names(call)
+				}
+			}
+		)*
+
+		impl crate::support::GetCallName for RuntimeCall {
+			fn pallet_name(&self) -> &'static str {
+				match self {
+					#(
+						RuntimeCall::#call_names(_) => stringify!(#call_names)
+					),*
+				}
+			}
+
+			fn call_name(&self) -> &'static str {
+				match self {
+					#(
+						RuntimeCall::#call_names(call) => call.name()
+					),*
+				}
+			}
+		}
+
+		impl PartialEq for RuntimeCall {
+			fn eq(&self, other: &Self) -> bool {
+				match (self, other) {
+					#(
+						(RuntimeCall::#call_names(call), RuntimeCall::#call_names(other)) => call == other,
+					)*
+					_ => false,
+				}
+			}
+		}
+
+		impl Eq for RuntimeCall {}
+
+		impl core::fmt::Debug for RuntimeCall {
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						RuntimeCall::#call_names(call) => f
+							.debug_tuple(stringify!(#call_names))
+							.field(call)
+							.finish()
+					),*
+				}
+			}
+		}
+	};
+
+    // This quote block gives access to each pallet stored in the `Runtime` struct, including
+    // system. Since pallets are configured by the runtime itself, this is how pallets reach each
+    // other: a pallet can require `T: AsMut<other_pallet::Pallet<T>>` in its `Config`.
+    let access_impl =
+        quote! {
+		impl AsRef<#system_type> for #runtime_struct {
+			fn as_ref(&self) -> &#system_type {
+				&self.#system_name
+			}
+		}
+
+		impl AsMut<#system_type> for #runtime_struct {
+			fn as_mut(&mut self) -> &mut #system_type {
+				&mut self.#system_name
+			}
+		}
+
+		#(
+			impl AsRef<#pallet_types> for #runtime_struct {
+				fn as_ref(&self) -> &#pallet_types {
+					&self.#pallet_names
+				}
+			}
+
+			impl AsMut<#pallet_types> for #runtime_struct {
+				fn as_mut(&mut self) -> &mut #pallet_types {
+					&mut self.#pallet_names
+				}
+			}
+		)*
+	};
+
     // We combine and return all the generated code.
-    (quote! {
+    quote! {
 		#dispatch_impl
+		#traits_impl
+		#encode_impl
 		#runtime_impl
-	}).into()
+		#access_impl
+	}
 }
@@ -1,15 +1,13 @@
 pub mod expand;
 pub mod parse;
 
+use quote::ToTokens;
+
 /// See the `fn runtime` docs at the `lib.rs` of this crate for a high level definition.
 pub fn runtime(
     _attr: proc_macro::TokenStream,
     item: proc_macro::TokenStream
 ) -> proc_macro::TokenStream {
-    // The final expanded code will be placed here.
-    // Since our macro only adds new code, our final product will contain all of our old code too,
-    // hence we clone `item`.
-    let mut finished = item.clone();
     let item_mod = syn::parse_macro_input!(item as syn::Item);
 
     // First we parse the `Runtime` struct...
@@ -19,7 +17,16 @@ pub fn runtime(
         Err(e) => e.to_compile_error().into(),
     };
 
+    // Since our macro only adds new code, our final product will contain all of our old code too,
+    // except for the attributes we use to mark pallets, which the compiler does not know.
+    let mut finished: proc_macro::TokenStream = match item_mod {
+        syn::Item::Struct(item_struct) => {
+            parse::strip_pallet_attrs(item_struct).into_token_stream().into()
+        }
+        item => item.into_token_stream().into(),
+    };
+
     // Add our generated code to the end, and return the final result.
     finished.extend(generated);
-    return finished;
+    finished
 }
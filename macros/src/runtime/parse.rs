@@ -1,3 +1,4 @@
+use quote::ToTokens;
 use syn::spanned::Spanned;
 
 /// This object will collect all the information we need to keep while parsing the `Runtime` struct.
@@ -5,9 +6,26 @@ use syn::spanned::Spanned;
 pub struct RuntimeDef {
     /// This is the name of the struct used by the user. We mostly assume it is `Runtime`.
     pub runtime_struct: syn::Ident,
+    /// This is the name and type of the system pallet, which is the field marked with
+    /// `#[runtime::system]`, or the field named `system`.
+    pub system: (syn::Ident, syn::Type),
+    /// This is the path to the `Config` trait of the system pallet, like `system::Config` for
+    /// `system::Pallet<Self>`.
+    pub system_config: syn::Path,
+    /// This is the `Call` type of the system pallet, like `system::Call<Runtime>`.
+    pub system_call: syn::Type,
     /// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
     /// list, but during parsing we check that system exists.
     pub pallets: Vec<(syn::Ident, syn::Type)>,
+    /// This is the `Call` type of each pallet in `pallets`, like `balances::Call<Runtime, I>` for
+    /// `balances::Pallet<Self, I>`.
+    pub pallet_calls: Vec<syn::Type>,
+    /// This is the index of each pallet in `pallets`, from its `#[pallet_index(n)]` attribute, or
+    /// else the lowest index no other pallet uses. System always has the index 0.
+    pub pallet_indices: Vec<u8>,
+    /// This is the list of pallets marked with `#[inherent]`, which implement
+    /// `support::ProvideInherent`.
+    pub inherents: Vec<(syn::Ident, syn::Type)>,
 }
 
 impl RuntimeDef {
@@ -19,49 +37,211 @@ impl RuntimeDef {
             return Err(syn::Error::new(item.span(), "Invalid runtime, expected item struct"));
         };
 
-        // We check that the `Runtime` includes the `system` pallet as the first item.
-        check_system(&item_struct)?;
+        // We check that the `Runtime` includes the `system` pallet, and find it.
+        let system = find_system(&item_struct)?;
+        let system_config = config_path(&system.1)?;
 
         let runtime_struct = item_struct.ident;
+        let system_call = call_type(&system.1, &runtime_struct)?;
+
+        // We skip `system`, which we found in `find_system`.
+        let fields = item_struct.fields
+            .into_iter()
+            .filter(|field| field.ident.as_ref() != Some(&system.0))
+            .collect::<Vec<_>>();
+
+        // First we collect the explicit indices, so that pallets without one can be given an
+        // index no other pallet uses, wherever they are in the struct.
+        let mut explicit_indices = vec![];
+        for field in &fields {
+            let index = get_pallet_index(&field.attrs)?;
+            if let Some(index) = index {
+                let span = field.ident.span();
+                if index == 0 {
+                    let msg = "Pallet index 0 is reserved for the system pallet";
+                    return Err(syn::Error::new(span, msg));
+                }
+                if explicit_indices.contains(&Some(index)) {
+                    let msg = format!("Duplicate pallet index {}", index);
+                    return Err(syn::Error::new(span, msg));
+                }
+            }
+            explicit_indices.push(index);
+        }
 
         // Here is where we will store a list of all the pallets.
         let mut pallets = vec![];
-        // We skip `system`, which we ensure is the first field in `check_system`.
-        for field in item_struct.fields.into_iter().skip(1) {
+        let mut pallet_calls = vec![];
+        let mut pallet_indices: Vec<u8> = vec![];
+        let mut inherents = vec![];
+        for (field, index) in fields.into_iter().zip(explicit_indices.iter()) {
             if let Some(ident) = field.ident {
+                // Pallets without an explicit index get the lowest free one, so an index should be
+                // given to pallets which may be moved, or which pallets may be added before.
+                let index = match index {
+                    Some(index) => *index,
+                    None => (1..=u8::MAX)
+                        .find(|index| {
+                            !explicit_indices.contains(&Some(*index)) &&
+                                !pallet_indices.contains(index)
+                        })
+                        .ok_or_else(|| {
+                            syn::Error::new(ident.span(), "Too many pallets, expected at most 255")
+                        })?,
+                };
+                pallet_indices.push(index);
+
+                if field.attrs.iter().any(is_inherent_attr) {
+                    inherents.push((ident.clone(), field.ty.clone()));
+                }
+                pallet_calls.push(call_type(&field.ty, &runtime_struct)?);
                 pallets.push((ident, field.ty));
             }
         }
 
-        Ok(Self { runtime_struct, pallets })
+        Ok(Self {
+            runtime_struct,
+            system,
+            system_config,
+            system_call,
+            pallets,
+            pallet_calls,
+            pallet_indices,
+            inherents,
+        })
     }
 }
 
-/// This function checks that the `system` pallet is the first pallet included in the `Runtime`
-/// struct. We make many assumptions about the `system` pallet in order to keep these macros simple.
-/// For example, we assume that the system pallet has no callable functions, and that it contains
-/// specific functions like incrementing the block number and a user's nonce.
+/// This function finds the `system` pallet included in the `Runtime` struct: the field marked with
+/// `#[runtime::system]`, or else the field named `system`. We make many assumptions about the
+/// `system` pallet in order to keep these macros simple. For example, we assume that the system
+/// pallet has a `Call` like every other pallet, and that it contains specific functions like
+/// incrementing the block number and a user's nonce.
 ///
 /// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
-fn check_system(item_struct: &syn::ItemStruct) -> syn::Result<()> {
-    // Extract the name of the first field in the `Runtime` struct.
-    let first_field_name = if let Some(first_field) = item_struct.fields.iter().next() {
-        if let Some(field_name) = &first_field.ident {
-            field_name.to_string()
-        } else {
-            let msg = "first field is expected to have the name system";
-            return Err(syn::Error::new(item_struct.span(), msg));
-        }
-    } else {
-        let msg = "runtime struct is expected to have fields";
+fn find_system(item_struct: &syn::ItemStruct) -> syn::Result<(syn::Ident, syn::Type)> {
+    let syn::Fields::Named(fields) = &item_struct.fields else {
+        let msg = "runtime struct is expected to have named fields";
         return Err(syn::Error::new(item_struct.span(), msg));
     };
 
-    // Check if the first field is named "system"
-    if first_field_name != "system" {
-        let msg = "first field is expected to be named system";
-        return Err(syn::Error::new(item_struct.span(), msg));
+    let mut marked = fields.named.iter().filter(|field| field.attrs.iter().any(is_system_attr));
+    let system = match (marked.next(), marked.next()) {
+        (Some(_), Some(other)) => {
+            let msg = "only one field can be marked with `#[runtime::system]`";
+            return Err(syn::Error::new(other.span(), msg));
+        }
+        (Some(field), None) => field,
+        (None, _) => {
+            let named_system = fields.named
+                .iter()
+                .find(|field| field.ident.as_ref().is_some_and(|ident| ident == "system"));
+            match named_system {
+                Some(field) => field,
+                None => {
+                    let msg =
+                        "runtime struct is expected to have a system pallet, named `system` or \
+                        marked with `#[runtime::system]`";
+                    return Err(syn::Error::new(item_struct.span(), msg));
+                }
+            }
+        }
+    };
+
+    let ident = system.ident.clone().expect("fields are named");
+    Ok((ident, system.ty.clone()))
+}
+
+/// Get the path to the `Config` trait of a pallet from its type, by replacing `Pallet<..>` with
+/// `Config` in its path.
+fn config_path(pallet_type: &syn::Type) -> syn::Result<syn::Path> {
+    let msg = "Invalid runtime, expected the system pallet struct, like `system::Pallet<Self>`";
+    let syn::Type::Path(type_path) = pallet_type else {
+        return Err(syn::Error::new(pallet_type.span(), msg));
+    };
+    let mut path = type_path.path.clone();
+    let last = path.segments
+        .last_mut()
+        .ok_or_else(|| syn::Error::new(pallet_type.span(), msg))?;
+    last.ident = syn::Ident::new("Config", last.ident.span());
+    last.arguments = syn::PathArguments::None;
+    Ok(path)
+}
+
+/// Whether this is the `#[runtime::system]` attribute, which marks the system pallet.
+fn is_system_attr(attr: &syn::Attribute) -> bool {
+    let segments = attr.path().segments.iter().map(|segment| segment.ident.to_string());
+    segments.eq(["runtime", "system"])
+}
+
+/// Whether this is the `#[inherent]` attribute, which marks a pallet providing an inherent.
+fn is_inherent_attr(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("inherent")
+}
+
+/// Get the `Call` type of a pallet from the type of its field, by replacing `Pallet` with `Call` in
+/// its path. The same pallet can be included several times with different instances, like
+/// `balances::Pallet<Self, Instance2>`, each with its own `Call` type. Since the `Call` types are
+/// used outside of the `Runtime` struct, `Self` is replaced with the name of the runtime.
+fn call_type(pallet_type: &syn::Type, runtime_struct: &syn::Ident) -> syn::Result<syn::Type> {
+    let msg = "Invalid runtime, expected the pallet struct, like `balances::Pallet<Self>`";
+    let syn::Type::Path(type_path) = pallet_type else {
+        return Err(syn::Error::new(pallet_type.span(), msg));
+    };
+    let mut path = type_path.path.clone();
+    let last = path.segments
+        .last_mut()
+        .ok_or_else(|| syn::Error::new(pallet_type.span(), msg))?;
+    last.ident = syn::Ident::new("Call", last.ident.span());
+    syn::parse2(replace_self(path.into_token_stream(), runtime_struct))
+}
+
+/// Replace every `Self` in `tokens` with `runtime_struct`.
+fn replace_self(
+    tokens: proc_macro2::TokenStream,
+    runtime_struct: &syn::Ident
+) -> proc_macro2::TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            proc_macro2::TokenTree::Ident(ident) if ident == "Self" => {
+                proc_macro2::TokenTree::Ident(runtime_struct.clone())
+            }
+            proc_macro2::TokenTree::Group(group) => {
+                let stream = replace_self(group.stream(), runtime_struct);
+                let mut replaced = proc_macro2::Group::new(group.delimiter(), stream);
+                replaced.set_span(group.span());
+                proc_macro2::TokenTree::Group(replaced)
+            }
+            token => token,
+        })
+        .collect()
+}
+
+/// Get the index of a pallet from its `#[pallet_index(n)]` attribute, if it has one.
+fn get_pallet_index(attrs: &[syn::Attribute]) -> syn::Result<Option<u8>> {
+    let mut attrs = attrs.iter().filter(|attr| is_pallet_index_attr(attr));
+    let Some(attr) = attrs.next() else {
+        return Ok(None);
+    };
+    if let Some(other) = attrs.next() {
+        return Err(syn::Error::new(other.span(), "Invalid runtime, duplicate `#[pallet_index]`"));
     }
+    attr.parse_args::<syn::LitInt>()?.base10_parse::<u8>().map(Some)
+}
+
+/// Whether this is the `#[pallet_index(n)]` attribute, which gives the index of a pallet.
+fn is_pallet_index_attr(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("pallet_index")
+}
 
-    Ok(())
+/// Remove the attributes which only have a meaning to this macro from the fields of the `Runtime`
+/// struct, since the compiler does not know them.
+pub fn strip_pallet_attrs(mut item_struct: syn::ItemStruct) -> syn::ItemStruct {
+    for field in item_struct.fields.iter_mut() {
+        field.attrs.retain(|attr| {
+            !is_inherent_attr(attr) && !is_pallet_index_attr(attr) && !is_system_attr(attr)
+        });
+    }
+    item_struct
 }
@@ -0,0 +1,80 @@
+use super::parse::StorageDef;
+use quote::quote;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_storage(def: StorageDef) -> proc_macro2::TokenStream {
+    let StorageDef { pallet_struct, generics, fields } = def;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // This is a vector of the expressions initializing each field. Storage items are created in
+    // the store of the runtime, under the prefix of the pallet and their own name, while other
+    // fields start from their `Default`.
+    let field_init = fields
+        .iter()
+        .map(|field| {
+            let name = &field.name;
+            let ty = &field.ty;
+            if field.is_storage {
+                quote! { #name: <#ty>::new(store, pallet, stringify!(#name)) }
+            } else {
+                quote! { #name: Default::default() }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // The second type parameter of the pallet, if any, is its instance, which has its own storage.
+    let instance_prefix = match generics.type_params().nth(1) {
+        Some(instance) => {
+            let instance = &instance.ident;
+            quote! { <#instance as crate::support::Instance>::PREFIX }
+        }
+        None => quote! { "" },
+    };
+
+    // These are the names, types and doc comments of the storage items, for the metadata.
+    let storage_fields = fields
+        .iter()
+        .filter(|field| field.is_storage)
+        .collect::<Vec<_>>();
+    let storage_name = storage_fields
+        .iter()
+        .map(|field| &field.name)
+        .collect::<Vec<_>>();
+    let storage_type = storage_fields
+        .iter()
+        .map(|field| &field.ty)
+        .collect::<Vec<_>>();
+    let storage_docs = storage_fields
+        .iter()
+        .map(|field| &field.docs)
+        .collect::<Vec<_>>();
+
+    quote! {
+		impl #impl_generics crate::metadata::PalletStorageMetadata
+			for #pallet_struct #ty_generics #where_clause
+		{
+			fn storage_metadata() -> Vec<crate::metadata::StorageEntryMetadata> {
+				vec![
+					#(
+						crate::metadata::StorageEntryMetadata::new::<#storage_type>(
+							stringify!(#storage_name),
+							vec![ #( #storage_docs ),* ],
+						)
+					),*
+				]
+			}
+		}
+
+		impl #impl_generics #pallet_struct #ty_generics #where_clause {
+			/// Create a new instance of the pallet, with its storage items in `store`.
+			pub fn new(store: &crate::storage::Store) -> Self {
+				// The prefix of the pallet is the name of its module, and the prefix of its instance.
+				let module = module_path!().rsplit("::").next().unwrap_or_default();
+				let pallet = &format!("{}{}", module, #instance_prefix);
+				Self {
+					#( #field_init ),*
+				}
+			}
+		}
+	}
+}
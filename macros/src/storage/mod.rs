@@ -0,0 +1,24 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn storage(
+    _attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream
+) -> proc_macro::TokenStream {
+    // Since our macro only adds new code, our final product will contain all of our old code too,
+    // hence we clone `item`.
+    let mut finished = item.clone();
+    let item_struct = syn::parse_macro_input!(item as syn::Item);
+
+    // First we parse the storage items of the pallet...
+    let generated: proc_macro::TokenStream = match parse::StorageDef::try_from(item_struct) {
+        // ..then we generate our new code.
+        Ok(def) => expand::expand_storage(def).into(),
+        Err(e) => e.to_compile_error().into(),
+    };
+
+    // Add our generated code to the end, and return the final result.
+    finished.extend(generated);
+    finished
+}
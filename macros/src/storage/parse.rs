@@ -0,0 +1,72 @@
+use syn::spanned::Spanned;
+
+/// The names of the storage item types, which we initialize with the prefix of the pallet.
+const STORAGE_TYPES: [&str; 3] = ["StorageValue", "StorageMap", "StorageDoubleMap"];
+
+/// This object will collect all the information we need to keep while parsing the storage of a
+/// pallet.
+#[derive(Debug)]
+pub struct StorageDef {
+    /// This is the name of the pallet struct holding the storage. We mostly assume it is `Pallet`.
+    pub pallet_struct: syn::Ident,
+    /// The generics of the pallet struct, usually `<T: Config>`.
+    pub generics: syn::Generics,
+    /// The fields of the pallet struct. See `FieldDef`.
+    pub fields: Vec<FieldDef>,
+}
+
+/// This is the metadata we keep about each field of the pallet struct.
+#[derive(Debug)]
+pub struct FieldDef {
+    /// The field name, which is also the name of the storage item.
+    pub name: syn::Ident,
+    /// The type of the field.
+    pub ty: syn::Type,
+    /// Whether the field is a storage item. Other fields are initialized with their `Default`.
+    pub is_storage: bool,
+    /// The lines of the doc comments of the field, for the metadata.
+    pub docs: Vec<String>,
+}
+
+impl StorageDef {
+    pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+        // First we check that we are parsing a struct with named fields.
+        let item_struct = if let syn::Item::Struct(item) = item {
+            item
+        } else {
+            return Err(syn::Error::new(item.span(), "Invalid pallet::storage, expected struct"));
+        };
+        let named_fields = if let syn::Fields::Named(named) = item_struct.fields {
+            named.named
+        } else {
+            return Err(
+                syn::Error::new(
+                    item_struct.fields.span(),
+                    "Invalid pallet::storage, expected named fields"
+                )
+            );
+        };
+
+        let fields = named_fields
+            .into_iter()
+            .map(|field| {
+                // Fields are storage items when the last segment of their type is one of
+                // `STORAGE_TYPES`, however the type is imported.
+                let is_storage = match &field.ty {
+                    syn::Type::Path(tp) =>
+                        tp.path.segments
+                            .last()
+                            .is_some_and(|segment| {
+                                STORAGE_TYPES.iter().any(|name| segment.ident == name)
+                            }),
+                    _ => false,
+                };
+                let docs = crate::call::parse::get_doc_literals(&field.attrs);
+                let name = field.ident.expect("fields are named");
+                FieldDef { name, ty: field.ty, is_storage, docs }
+            })
+            .collect();
+
+        Ok(Self { pallet_struct: item_struct.ident, generics: item_struct.generics, fields })
+    }
+}
@@ -0,0 +1,161 @@
+use core::fmt::Debug;
+use num::traits::{ CheckedAdd, CheckedSub, Zero };
+use crate::storage::{ Decode, Encode, StorageDoubleMap, StorageMap };
+use crate::support::DispatchResult;
+
+pub trait Config: crate::balances::Config {
+    /// The identifier of an asset.
+    type AssetId: Ord + Copy + Debug + Encode + Decode;
+}
+
+/// An asset, which only its owner can mint.
+#[derive(Debug)]
+pub struct AssetDetails<T: Config> {
+    pub owner: T::AccountId,
+    pub supply: T::Balance,
+}
+
+impl<T: Config> Encode for AssetDetails<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.owner.encode_to(dest);
+        self.supply.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for AssetDetails<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let owner = Decode::decode(input)?;
+        Some(Self { owner, supply: Decode::decode(input)? })
+    }
+}
+
+/// This pallet keeps the balances of fungible assets other than the native currency of
+/// `balances`. Assets use the same `Balance` type as the native currency.
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The details of each asset.
+    assets: StorageMap<T::AssetId, AssetDetails<T>>,
+    /// The balance of each account in each asset. Zero balances are not stored.
+    accounts: StorageDoubleMap<T::AssetId, T::AccountId, T::Balance>,
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn asset(&self, id: &T::AssetId) -> Option<AssetDetails<T>> {
+        self.assets.get(id)
+    }
+
+    /// The balance of `who` in the asset `id`.
+    pub fn balance(&self, id: &T::AssetId, who: &T::AccountId) -> T::Balance {
+        self.accounts.get(id, who).unwrap_or(T::Balance::zero())
+    }
+
+    fn set_balance(&mut self, id: T::AssetId, who: &T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.accounts.remove(&id, who);
+        } else {
+            self.accounts.insert(&id, who, &amount);
+        }
+    }
+
+    /// Move `amount` of the asset `id` from `from` to `to`. This is used by other pallets, which
+    /// check themselves that the move is allowed.
+    pub fn do_transfer(
+        &mut self,
+        id: T::AssetId,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: T::Balance
+    ) -> DispatchResult {
+        if self.asset(&id).is_none() {
+            return Err("Asset does not exist");
+        }
+        let new_from_balance = self
+            .balance(&id, from)
+            .checked_sub(&amount)
+            .ok_or("Not enough funds.")?;
+        if from == to {
+            return Ok(());
+        }
+        let new_to_balance = self.balance(&id, to).checked_add(&amount).ok_or("Overflow.")?;
+
+        self.set_balance(id, from, new_from_balance);
+        self.set_balance(id, to, new_to_balance);
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Create the asset `id`, owned by the caller.
+    #[call_index(0)]
+    pub fn create(&mut self, caller: T::AccountId, id: T::AssetId) -> DispatchResult {
+        if self.asset(&id).is_some() {
+            return Err("Asset already exists");
+        }
+        self.assets.insert(&id, &AssetDetails { owner: caller, supply: T::Balance::zero() });
+        Ok(())
+    }
+
+    /// Create `amount` of the asset `id` for `beneficiary`. Only the owner of the asset can mint.
+    #[call_index(1)]
+    pub fn mint(
+        &mut self,
+        caller: T::AccountId,
+        id: T::AssetId,
+        beneficiary: T::AccountId,
+        amount: T::Balance
+    ) -> DispatchResult {
+        let mut details = self.asset(&id).ok_or("Asset does not exist")?;
+        if details.owner != caller {
+            return Err("You are not the owner of this asset");
+        }
+        details.supply = details.supply.checked_add(&amount).ok_or("Overflow.")?;
+        let new_balance = self.balance(&id, &beneficiary).checked_add(&amount).ok_or("Overflow.")?;
+        self.assets.insert(&id, &details);
+        self.set_balance(id, &beneficiary, new_balance);
+        Ok(())
+    }
+
+    #[call_index(2)]
+    pub fn transfer(
+        &mut self,
+        caller: T::AccountId,
+        id: T::AssetId,
+        to: T::AccountId,
+        amount: T::Balance
+    ) -> DispatchResult {
+        self.do_transfer(id, &caller, &to, amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Runtime;
+
+    #[test]
+    fn create_mint_and_transfer() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        assert_eq!(runtime.assets.create(alice.clone(), 1), Ok(()));
+        assert_eq!(runtime.assets.create(bob.clone(), 1), Err("Asset already exists"));
+        assert_eq!(
+            runtime.assets.mint(bob.clone(), 1, bob.clone(), 100),
+            Err("You are not the owner of this asset")
+        );
+        assert_eq!(runtime.assets.mint(alice.clone(), 1, alice.clone(), 100), Ok(()));
+        assert_eq!(runtime.assets.asset(&1).unwrap().supply, 100);
+
+        assert_eq!(runtime.assets.transfer(alice.clone(), 1, bob.clone(), 30), Ok(()));
+        assert_eq!(
+            runtime.assets.transfer(bob.clone(), 1, alice.clone(), 31),
+            Err("Not enough funds.")
+        );
+        assert_eq!(runtime.assets.balance(&1, &alice), 70);
+        assert_eq!(runtime.assets.balance(&1, &bob), 30);
+    }
+}
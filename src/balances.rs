@@ -1,34 +1,167 @@
-use std::collections::BTreeMap;
-use num::traits::{ CheckedAdd, CheckedSub, Zero };
+use core::{ fmt::Debug, marker::PhantomData };
+use num::traits::{ CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Saturating, Zero };
+use crate::storage::{ Decode, Encode, StorageDoubleMap, StorageMap };
+use crate::support::{ Instance, Origin };
 
-pub trait Config: crate::system::Config {
-    type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+/// The configuration of an instance `I` of this pallet. The default instance is `()`.
+pub trait Config<I: Instance = ()>: crate::system::Config {
+    type Balance: Zero +
+        CheckedSub +
+        CheckedAdd +
+        CheckedMul +
+        CheckedDiv +
+        Saturating +
+        From<u32> +
+        Ord +
+        Copy +
+        Encode +
+        Decode +
+        Debug;
 }
 
+/// An identifier for a lock on the funds of an account, so that pallets can lock funds of the same
+/// account independently.
+pub type LockIdentifier = [u8; 8];
+
+#[macros::storage]
 #[derive(Debug)]
-pub struct Pallet<T: Config> {
-    balances: BTreeMap<T::AccountId, T::Balance>,
+pub struct Pallet<T: Config<I>, I: Instance = ()> {
+    /// The free funds of each account.
+    balances: StorageMap<T::AccountId, T::Balance>,
+    /// The reserved funds of each account.
+    reserved: StorageMap<T::AccountId, T::Balance>,
+    /// The locks on the funds of each account, by their identifier.
+    locks: StorageDoubleMap<T::AccountId, LockIdentifier, T::Balance>,
+    _instance: PhantomData<I>,
 }
 
-impl<T: Config> Pallet<T> {
-    /// Create a new instance of the balances module.
-    pub fn new() -> Self {
-        Self {
-            balances: BTreeMap::new(),
+impl<T: Config<I>, I: Instance> Pallet<T, I> {
+    pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
+        self.balances.insert(who, &amount);
+    }
+
+    pub fn balance(&self, who: &T::AccountId) -> T::Balance {
+        self.balances.get(who).unwrap_or(T::Balance::zero())
+    }
+
+    /// The funds of `who` which are reserved, for example as a deposit. Reserved funds are not
+    /// part of `balance`.
+    pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.reserved.get(who).unwrap_or(T::Balance::zero())
+    }
+
+    fn set_reserved_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.reserved.remove(who);
+        } else {
+            self.reserved.insert(who, &amount);
         }
     }
 
-    pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-        self.balances.insert(who.clone(), amount);
+    /// Move `amount` of the funds of `who` to its reserved balance. Locked funds cannot be
+    /// reserved.
+    pub fn reserve(
+        &mut self,
+        who: &T::AccountId,
+        amount: T::Balance
+    ) -> crate::support::DispatchResult {
+        let new_balance = self.balance(who).checked_sub(&amount).ok_or("Not enough funds.")?;
+        if new_balance < self.locked(who) {
+            return Err("Funds are locked.");
+        }
+        let new_reserved = self.reserved_balance(who).checked_add(&amount).ok_or("Overflow.")?;
+
+        self.set_balance(who, new_balance);
+        self.set_reserved_balance(who, new_reserved);
+        Ok(())
     }
 
-    pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-        *self.balances.get(who).unwrap_or(&T::Balance::zero())
+    /// Move up to `amount` of the reserved funds of `who` back to its balance. Returns the amount
+    /// which was moved.
+    pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let reserved = self.reserved_balance(who);
+        let unreserved = amount.min(reserved);
+        // The funds were part of the balance before being reserved, so this cannot overflow.
+        let new_balance = self.balance(who) + unreserved;
+
+        self.set_reserved_balance(who, reserved - unreserved);
+        self.set_balance(who, new_balance);
+        unreserved
+    }
+
+    /// Destroy up to `amount` of the reserved funds of `who`. Returns the amount which was
+    /// destroyed.
+    pub fn slash_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let reserved = self.reserved_balance(who);
+        let slashed = amount.min(reserved);
+        self.set_reserved_balance(who, reserved - slashed);
+        slashed
+    }
+
+    /// Move up to `amount` of the reserved funds of `from` to the balance of `to`. Returns the
+    /// amount which was moved.
+    pub fn repatriate_reserved(
+        &mut self,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: T::Balance
+    ) -> Result<T::Balance, &'static str> {
+        let reserved = self.reserved_balance(from);
+        let moved = amount.min(reserved);
+        let new_to_balance = self.balance(to).checked_add(&moved).ok_or("Overflow.")?;
+
+        self.set_reserved_balance(from, reserved - moved);
+        self.set_balance(to, new_to_balance);
+        Ok(moved)
+    }
+
+    /// Create `amount` of new funds in the account of `who`.
+    pub fn mint(
+        &mut self,
+        who: &T::AccountId,
+        amount: T::Balance
+    ) -> crate::support::DispatchResult {
+        let new_balance = self.balance(who).checked_add(&amount).ok_or("Overflow.")?;
+        self.set_balance(who, new_balance);
+        Ok(())
+    }
+
+    /// Destroy up to `amount` of the funds of `who`, even if they are locked. Returns the amount
+    /// which was destroyed.
+    pub fn burn(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let balance = self.balance(who);
+        let burned = amount.min(balance);
+        self.set_balance(who, balance - burned);
+        burned
+    }
+
+    /// Lock `amount` of the funds of `who` under `id`, replacing any previous lock with that id.
+    /// Locked funds stay in the account, but cannot be transferred.
+    pub fn set_lock(&mut self, id: LockIdentifier, who: &T::AccountId, amount: T::Balance) {
+        self.locks.insert(who, &id, &amount);
+    }
+
+    pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+        self.locks.remove(who, &id);
+    }
+
+    /// The amount of funds of `who` which cannot be transferred. Locks overlap, so this is the
+    /// largest of them.
+    pub fn locked(&self, who: &T::AccountId) -> T::Balance {
+        self.locks
+            .iter_prefix(who)
+            .into_iter()
+            .map(|(_, amount)| amount)
+            .max()
+            .unwrap_or(T::Balance::zero())
     }
 }
 
+impl<T: Config<I>, I: Instance> crate::support::Hooks<T> for Pallet<T, I> {}
+
 #[macros::call]
-impl<T: Config> Pallet<T> {
+impl<T: Config<I>, I: Instance> Pallet<T, I> {
+    #[call_index(0)]
     pub fn transfer(
         &mut self,
         caller: T::AccountId,
@@ -36,20 +169,40 @@ impl<T: Config> Pallet<T> {
         amount: T::Balance
     ) -> crate::support::DispatchResult {
         let caller_balance = self.balance(&caller);
-        let to_balance = self.balance(&to);
-
         let new_caller_balance = caller_balance.checked_sub(&amount).ok_or("Not enough funds.")?;
-        let new_to_balance = to_balance.checked_add(&amount).ok_or("Overflow.")?;
+        if new_caller_balance < self.locked(&caller) {
+            return Err("Funds are locked.");
+        }
+        // The funds would be credited back to the caller, which must not mint them.
+        if caller == to {
+            return Ok(());
+        }
+        let new_to_balance = self.balance(&to).checked_add(&amount).ok_or("Overflow.")?;
 
         self.set_balance(&caller, new_caller_balance);
         self.set_balance(&to, new_to_balance);
         Ok(())
     }
+
+    /// Set the balance of `who` to `amount`. Only root can make this call.
+    #[call_index(1)]
+    pub fn force_set_balance(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        amount: T::Balance
+    ) -> crate::support::DispatchResult {
+        origin.ensure_root()?;
+        self.set_balance(&who, amount);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Pallet;
+    use crate::storage::Store;
+    use crate::support::Origin;
     use crate::types::{ AccountId, Balance, BlockNumber, Nonce };
 
     struct TestConfig;
@@ -57,6 +210,10 @@ mod tests {
         type AccountId = AccountId;
         type BlockNumber = BlockNumber;
         type Nonce = Nonce;
+        type BaseCallFilter = ();
+
+        const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+        const EXTRINSIC_WEIGHT: crate::support::Weight = 10;
     }
 
     impl super::Config for TestConfig {
@@ -64,7 +221,7 @@ mod tests {
     }
     #[test]
     fn init_balances() {
-        let mut balances = Pallet::<TestConfig>::new();
+        let mut balances = Pallet::<TestConfig>::new(&Store::new());
 
         assert_eq!(balances.balance(&"alice".to_string()), 0);
 
@@ -75,7 +232,7 @@ mod tests {
 
     #[test]
     fn transfer_balance() {
-        let mut balances = Pallet::<TestConfig>::new();
+        let mut balances = Pallet::<TestConfig>::new(&Store::new());
 
         assert_eq!(
             balances.transfer("alice".to_string(), "bob".to_string(), 100),
@@ -86,5 +243,104 @@ mod tests {
         assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 50), Ok(()));
         assert_eq!(balances.balance(&"alice".to_string()), 50);
         assert_eq!(balances.balance(&"bob".to_string()), 50);
+
+        // Transferring to yourself leaves your balance as it is.
+        assert_eq!(balances.transfer("alice".to_string(), "alice".to_string(), 50), Ok(()));
+        assert_eq!(balances.balance(&"alice".to_string()), 50);
+        assert_eq!(
+            balances.transfer("alice".to_string(), "alice".to_string(), 60),
+            Err("Not enough funds.")
+        );
+    }
+
+    #[test]
+    fn locked_funds_cannot_be_transferred() {
+        let alice = "alice".to_string();
+        let mut balances = Pallet::<TestConfig>::new(&Store::new());
+        balances.set_balance(&alice, 100);
+
+        balances.set_lock(*b"first   ", &alice, 50);
+        balances.set_lock(*b"second  ", &alice, 70);
+        assert_eq!(balances.locked(&alice), 70);
+        assert_eq!(
+            balances.transfer(alice.clone(), "bob".to_string(), 40),
+            Err("Funds are locked.")
+        );
+        assert_eq!(balances.transfer(alice.clone(), "bob".to_string(), 30), Ok(()));
+
+        balances.remove_lock(*b"second  ", &alice);
+        assert_eq!(balances.locked(&alice), 50);
+        assert_eq!(balances.transfer(alice.clone(), "bob".to_string(), 20), Ok(()));
+        assert_eq!(balances.balance(&alice), 50);
+    }
+
+    #[test]
+    fn mint_and_burn() {
+        let alice = "alice".to_string();
+        let mut balances = Pallet::<TestConfig>::new(&Store::new());
+
+        assert_eq!(balances.mint(&alice, 100), Ok(()));
+        assert_eq!(balances.mint(&alice, u128::MAX), Err("Overflow."));
+
+        // Burning ignores locks, but cannot burn more than the balance.
+        balances.set_lock(*b"lock    ", &alice, 100);
+        assert_eq!(balances.burn(&alice, 30), 30);
+        assert_eq!(balances.burn(&alice, 100), 70);
+        assert_eq!(balances.balance(&alice), 0);
+    }
+
+    #[test]
+    fn reserve_and_unreserve() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let mut balances = Pallet::<TestConfig>::new(&Store::new());
+        balances.set_balance(&alice, 100);
+        balances.set_lock(*b"lock    ", &alice, 50);
+
+        assert_eq!(balances.reserve(&alice, 60), Err("Funds are locked."));
+        assert_eq!(balances.reserve(&alice, 50), Ok(()));
+        assert_eq!(balances.balance(&alice), 50);
+        assert_eq!(balances.reserved_balance(&alice), 50);
+
+        assert_eq!(balances.unreserve(&alice, 10), 10);
+        assert_eq!(balances.slash_reserved(&alice, 10), 10);
+        assert_eq!(balances.repatriate_reserved(&alice, &bob, 100), Ok(30));
+        assert_eq!(balances.balance(&alice), 60);
+        assert_eq!(balances.reserved_balance(&alice), 0);
+        assert_eq!(balances.balance(&bob), 30);
+    }
+
+    #[test]
+    fn force_set_balance_needs_root() {
+        let alice = "alice".to_string();
+        let mut balances = Pallet::<TestConfig>::new(&Store::new());
+
+        assert_eq!(
+            balances.force_set_balance(Origin::Signed(alice.clone()), alice.clone(), 100),
+            Err("Expected the root origin")
+        );
+        assert_eq!(balances.force_set_balance(Origin::Root, alice.clone(), 100), Ok(()));
+        assert_eq!(balances.balance(&alice), 100);
+    }
+
+    #[test]
+    fn instances_are_independent() {
+        use crate::{ support::{ Dispatch, Instance2 }, Runtime, RuntimeCall };
+
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        runtime.credits.set_balance(&alice, 10);
+
+        let call = RuntimeCall::credits(super::Call::<Runtime, Instance2>::transfer {
+            to: bob.clone(),
+            amount: 5,
+        });
+        assert_eq!(runtime.dispatch(Origin::Signed(alice.clone()), call), Ok(()));
+        assert_eq!(runtime.credits.balance(&alice), 5);
+        assert_eq!(runtime.credits.balance(&bob), 5);
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert_eq!(runtime.balances.balance(&bob), 0);
     }
 }
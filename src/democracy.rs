@@ -0,0 +1,489 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+use num::traits::{ CheckedAdd, CheckedMul, Zero };
+use crate::balances;
+use crate::metadata::ConstantMetadata;
+use crate::storage::{ Decode, Encode, StorageMap, StorageValue };
+use crate::support::{ Dispatch, DispatchResult, Origin, Weight };
+
+/// The id of the lock this pallet puts on funds used to vote in `balances`.
+const DEMOCRACY_ID: balances::LockIdentifier = *b"democrac";
+
+/// The index of a public proposal.
+pub type PropIndex = u32;
+/// The index of a referendum.
+pub type ReferendumIndex = u32;
+
+pub trait Config: Sized +
+    crate::balances::Config +
+    AsMut<crate::system::Pallet<Self>> +
+    AsMut<crate::balances::Pallet<Self>> +
+    AsMut<Pallet<Self>> +
+    Dispatch<
+        Caller = Origin<<Self as crate::system::Config>::AccountId>,
+        Call = <Self as Config>::RuntimeCall
+    > {
+    /// The overarching call type, which is dispatched by root when a referendum passes.
+    type RuntimeCall: Debug + Encode + Decode;
+
+    /// The minimum deposit needed to make a proposal.
+    const MINIMUM_DEPOSIT: Self::Balance;
+    /// The number of blocks between launching the most backed proposal as a referendum.
+    const LAUNCH_PERIOD: Self::BlockNumber;
+    /// The number of blocks during which a referendum can be voted on.
+    const VOTING_PERIOD: Self::BlockNumber;
+    /// The number of blocks between a referendum passing and its call being enacted.
+    const ENACTMENT_DELAY: Self::BlockNumber;
+}
+
+/// A call proposed to be voted on. The proposer and each seconder have reserved `deposit`.
+#[derive(Debug)]
+pub struct Proposal<T: Config> {
+    pub index: PropIndex,
+    pub proposer: T::AccountId,
+    pub call: <T as Config>::RuntimeCall,
+    pub deposit: T::Balance,
+    pub seconds: Vec<T::AccountId>,
+}
+
+impl<T: Config> Proposal<T> {
+    /// The total deposit of the proposer and all seconders.
+    pub fn backing(&self) -> T::Balance {
+        let backers = T::Balance::from(self.seconds.len() as u32 + 1);
+        self.deposit.checked_mul(&backers).unwrap_or(self.deposit)
+    }
+}
+
+impl<T: Config> Encode for Proposal<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.index.encode_to(dest);
+        self.proposer.encode_to(dest);
+        self.call.encode_to(dest);
+        self.deposit.encode_to(dest);
+        self.seconds.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Proposal<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            index: Decode::decode(input)?,
+            proposer: Decode::decode(input)?,
+            call: Decode::decode(input)?,
+            deposit: Decode::decode(input)?,
+            seconds: Decode::decode(input)?,
+        })
+    }
+}
+
+/// A vote of `balance`, which stays locked until the referendum ends.
+#[derive(Debug)]
+pub struct Vote<T: Config> {
+    pub aye: bool,
+    pub balance: T::Balance,
+}
+
+impl<T: Config> Encode for Vote<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.aye.encode_to(dest);
+        self.balance.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Vote<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let aye = Decode::decode(input)?;
+        Some(Self { aye, balance: Decode::decode(input)? })
+    }
+}
+
+/// A proposal being voted on until block `end`.
+#[derive(Debug)]
+pub struct Referendum<T: Config> {
+    pub call: <T as Config>::RuntimeCall,
+    pub end: T::BlockNumber,
+    pub votes: BTreeMap<T::AccountId, Vote<T>>,
+}
+
+impl<T: Config> Encode for Referendum<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.call.encode_to(dest);
+        self.end.encode_to(dest);
+        self.votes.iter().collect::<Vec<_>>().encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Referendum<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let call = Decode::decode(input)?;
+        let end = Decode::decode(input)?;
+        let votes = Vec::<(T::AccountId, Vote<T>)>::decode(input)?;
+        Some(Self { call, end, votes: votes.into_iter().collect() })
+    }
+}
+
+impl<T: Config> Referendum<T> {
+    /// The total balance voting aye and nay, or `None` if either total overflows.
+    pub fn tally(&self) -> Option<(T::Balance, T::Balance)> {
+        let zero = (T::Balance::zero(), T::Balance::zero());
+        self.votes.values().try_fold(zero, |(ayes, nays), vote| {
+            if vote.aye {
+                Some((ayes.checked_add(&vote.balance)?, nays))
+            } else {
+                Some((ayes, nays.checked_add(&vote.balance)?))
+            }
+        })
+    }
+
+    /// A referendum passes with strictly more balance voting aye than nay. A referendum whose
+    /// tally overflows does not pass.
+    pub fn is_passing(&self) -> bool {
+        self.tally().is_some_and(|(ayes, nays)| ayes > nays)
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The proposals waiting to be launched as a referendum.
+    public_props: StorageValue<Vec<Proposal<T>>>,
+    /// The index of the next proposal.
+    next_prop_index: StorageValue<PropIndex>,
+    /// The referenda being voted on.
+    referenda: StorageMap<ReferendumIndex, Referendum<T>>,
+    /// The index of the next referendum.
+    next_referendum_index: StorageValue<ReferendumIndex>,
+    /// The block the last referendum was launched at.
+    last_launch: StorageValue<T::BlockNumber>,
+    /// The calls of passed referenda, by the block they are enacted at.
+    dispatch_queue: StorageMap<T::BlockNumber, Vec<<T as Config>::RuntimeCall>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// The proposals waiting to be launched as a referendum.
+    pub fn public_props(&self) -> Vec<Proposal<T>> {
+        self.public_props.get().unwrap_or_default()
+    }
+
+    /// The referendum at `index`, if it is being voted on.
+    pub fn referendum(&self, index: ReferendumIndex) -> Option<Referendum<T>> {
+        self.referenda.get(&index)
+    }
+
+    // Update the lock on the funds of `who` to its largest vote in an ongoing referendum.
+    fn update_lock(runtime: &mut T, who: &T::AccountId) {
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let locked = democracy.referenda
+            .iter()
+            .into_iter()
+            .filter_map(|(_, referendum)| referendum.votes.get(who).map(|vote| vote.balance))
+            .max();
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        match locked {
+            Some(locked) if !locked.is_zero() => balances.set_lock(DEMOCRACY_ID, who, locked),
+            _ => balances.remove_lock(DEMOCRACY_ID, who),
+        }
+    }
+
+    // Turn the most backed proposal into a referendum, returning the deposits of its backers. Ties
+    // go to the oldest proposal.
+    fn launch_referendum(runtime: &mut T, block_number: T::BlockNumber) {
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let mut public_props = democracy.public_props();
+        let mut best: Option<(usize, T::Balance)> = None;
+        for (i, proposal) in public_props.iter().enumerate() {
+            let backing = proposal.backing();
+            if best.is_none_or(|(_, best_backing)| backing > best_backing) {
+                best = Some((i, backing));
+            }
+        }
+        let Some((best, _)) = best else {
+            return;
+        };
+
+        let index = democracy.next_referendum_index.get().unwrap_or(0);
+        let Some(next_index) = index.checked_add(1) else {
+            return;
+        };
+        democracy.next_referendum_index.put(&next_index);
+        let Proposal { proposer, call, deposit, seconds, .. } = public_props.remove(best);
+        democracy.public_props.put(&public_props);
+        let end = block_number.checked_add(&T::VOTING_PERIOD).unwrap_or(block_number);
+        democracy.referenda.insert(&index, &Referendum { call, end, votes: BTreeMap::new() });
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        for backer in core::iter::once(&proposer).chain(seconds.iter()) {
+            balances.unreserve(backer, deposit);
+        }
+    }
+
+    // Tally the referenda which end at `block_number`, queueing the calls of the ones which pass.
+    fn close_referenda(runtime: &mut T, block_number: T::BlockNumber) {
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let ended = democracy.referenda
+            .iter()
+            .into_iter()
+            .filter(|(_, referendum)| referendum.end <= block_number)
+            .collect::<Vec<_>>();
+
+        for (index, referendum) in ended {
+            let democracy: &mut Pallet<T> = runtime.as_mut();
+            democracy.referenda.remove(&index);
+            if referendum.tally().is_none() {
+                eprintln!("Tally Error\n\tReferendum: {}\n\tError: Overflow", index);
+            }
+            let passing = referendum.is_passing();
+            let Referendum { call, votes, .. } = referendum;
+            if passing {
+                let when = block_number.checked_add(&T::ENACTMENT_DELAY).unwrap_or(block_number);
+                let mut calls = democracy.dispatch_queue.get(&when).unwrap_or_default();
+                calls.push(call);
+                democracy.dispatch_queue.insert(&when, &calls);
+            }
+            for voter in votes.keys() {
+                Self::update_lock(runtime, voter);
+            }
+        }
+    }
+
+    // Dispatch the calls of passed referenda which are due at `block_number`, as root.
+    fn enact(runtime: &mut T, block_number: T::BlockNumber) {
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let calls = democracy.dispatch_queue.get(&block_number).unwrap_or_default();
+        democracy.dispatch_queue.remove(&block_number);
+        for call in calls {
+            let _res = runtime.dispatch(Origin::Root, call).map_err(|e| {
+                eprintln!("Enactment Error\n\tBlock Number: {:?}\n\tError: {}", block_number, e)
+            });
+        }
+    }
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("MINIMUM_DEPOSIT", T::MINIMUM_DEPOSIT),
+            ConstantMetadata::new("LAUNCH_PERIOD", T::LAUNCH_PERIOD),
+            ConstantMetadata::new("VOTING_PERIOD", T::VOTING_PERIOD),
+            ConstantMetadata::new("ENACTMENT_DELAY", T::ENACTMENT_DELAY)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {
+    // Launch a referendum every `LAUNCH_PERIOD` blocks, close the referenda which have ended, and
+    // enact the ones which passed once their delay is over.
+    fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) -> Weight {
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let last_launch = democracy.last_launch.get().unwrap_or(T::BlockNumber::zero());
+        let next_launch = last_launch.checked_add(&T::LAUNCH_PERIOD);
+        if next_launch.is_some_and(|next_launch| block_number >= next_launch) {
+            democracy.last_launch.put(&block_number);
+            Self::launch_referendum(runtime, block_number);
+        }
+        Self::close_referenda(runtime, block_number);
+        Self::enact(runtime, block_number);
+        0
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Propose `call` to be voted on, reserving `deposit` from the caller until the proposal is
+    /// launched as a referendum.
+    #[allow(clippy::boxed_local)]
+    #[call_index(0)]
+    pub fn propose(
+        runtime: &mut T,
+        caller: T::AccountId,
+        call: Box<<T as Config>::RuntimeCall>,
+        deposit: T::Balance
+    ) -> DispatchResult {
+        if deposit < T::MINIMUM_DEPOSIT {
+            return Err("The deposit is below the minimum");
+        }
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let index = democracy.next_prop_index.get().unwrap_or(0);
+        let next_index = index.checked_add(1).ok_or("Overflow")?;
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.reserve(&caller, deposit)?;
+
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        democracy.next_prop_index.put(&next_index);
+        let mut public_props = democracy.public_props();
+        public_props.push(Proposal {
+            index,
+            proposer: caller,
+            call: *call,
+            deposit,
+            seconds: vec![],
+        });
+        democracy.public_props.put(&public_props);
+        Ok(())
+    }
+
+    /// Back the proposal at `index`, reserving the same deposit as its proposer.
+    #[call_index(1)]
+    pub fn second(runtime: &mut T, caller: T::AccountId, index: PropIndex) -> DispatchResult {
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let deposit = democracy.public_props()
+            .iter()
+            .find(|proposal| proposal.index == index)
+            .map(|proposal| proposal.deposit)
+            .ok_or("Proposal does not exist")?;
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.reserve(&caller, deposit)?;
+
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let mut public_props = democracy.public_props();
+        if let Some(proposal) = public_props.iter_mut().find(|p| p.index == index) {
+            proposal.seconds.push(caller);
+        }
+        democracy.public_props.put(&public_props);
+        Ok(())
+    }
+
+    /// Vote on the referendum at `index` with `balance`, which is locked until the referendum
+    /// ends. Voting again replaces the previous vote.
+    #[call_index(2)]
+    pub fn vote(
+        runtime: &mut T,
+        caller: T::AccountId,
+        index: ReferendumIndex,
+        aye: bool,
+        balance: T::Balance
+    ) -> DispatchResult {
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        if balance > balances.balance(&caller) {
+            return Err("Not enough funds to vote");
+        }
+
+        let democracy: &mut Pallet<T> = runtime.as_mut();
+        let mut referendum = democracy.referendum(index).ok_or("Referendum does not exist")?;
+        referendum.votes.insert(caller.clone(), Vote { aye, balance });
+        democracy.referenda.insert(&index, &referendum);
+        Self::update_lock(runtime, &caller);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pallet;
+    use crate::{ balances, testing::run_to_block, Runtime, RuntimeCall };
+
+    fn set_balance(who: &str, amount: u128) -> Box<RuntimeCall> {
+        Box::new(
+            RuntimeCall::balances(balances::Call::force_set_balance {
+                who: who.to_string(),
+                amount,
+            })
+        )
+    }
+
+    #[test]
+    fn passed_referendum_is_enacted() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let charlie = "charlie".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&bob, 100);
+
+        assert_eq!(
+            Pallet::propose(&mut runtime, alice.clone(), set_balance("charlie", 1), 5),
+            Err("The deposit is below the minimum")
+        );
+        assert_eq!(
+            Pallet::propose(&mut runtime, alice.clone(), set_balance("charlie", 1), 10),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::propose(&mut runtime, bob.clone(), set_balance("charlie", 1_000), 10),
+            Ok(())
+        );
+        // Alice seconds the proposal of bob, which is then more backed.
+        assert_eq!(Pallet::second(&mut runtime, alice.clone(), 1), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+
+        // The proposal of bob is launched and the deposits of its backers are returned.
+        run_to_block(&mut runtime, 5);
+        assert_eq!(runtime.democracy.public_props().len(), 1);
+        assert!(runtime.democracy.referendum(0).is_some());
+        assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+        assert_eq!(runtime.balances.reserved_balance(&bob), 0);
+
+        assert_eq!(Pallet::vote(&mut runtime, alice.clone(), 0, true, 80), Ok(()));
+        assert_eq!(Pallet::vote(&mut runtime, bob.clone(), 0, false, 50), Ok(()));
+        assert_eq!(runtime.democracy.referendum(0).unwrap().tally(), Some((80, 50)));
+        assert_eq!(runtime.balances.locked(&alice), 80);
+
+        // The referendum ends at block 8, and is enacted by root at block 10.
+        run_to_block(&mut runtime, 8);
+        assert_eq!(runtime.balances.locked(&alice), 0);
+        assert_eq!(runtime.balances.balance(&charlie), 0);
+        run_to_block(&mut runtime, 10);
+        assert_eq!(runtime.balances.balance(&charlie), 1_000);
+    }
+
+    #[test]
+    fn rejected_referendum_is_not_enacted() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&bob, 100);
+
+        assert_eq!(
+            Pallet::propose(&mut runtime, alice.clone(), set_balance("charlie", 1_000), 10),
+            Ok(())
+        );
+        run_to_block(&mut runtime, 5);
+        assert_eq!(
+            Pallet::vote(&mut runtime, alice.clone(), 0, true, 1_000),
+            Err("Not enough funds to vote")
+        );
+
+        // A tie is not enough for a referendum to pass.
+        assert_eq!(Pallet::vote(&mut runtime, alice.clone(), 0, true, 50), Ok(()));
+        assert_eq!(Pallet::vote(&mut runtime, bob.clone(), 0, false, 50), Ok(()));
+        assert!(!runtime.democracy.referendum(0).unwrap().is_passing());
+
+        run_to_block(&mut runtime, 10);
+        assert!(runtime.democracy.referendum(0).is_none());
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 0);
+        assert_eq!(
+            Pallet::vote(&mut runtime, alice, 0, true, 50),
+            Err("Referendum does not exist")
+        );
+    }
+
+    #[test]
+    fn overflowing_tally_does_not_pass() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, u128::MAX);
+        runtime.balances.set_balance(&bob, u128::MAX);
+        assert_eq!(
+            Pallet::propose(&mut runtime, alice.clone(), set_balance("charlie", 1_000), 10),
+            Ok(())
+        );
+        run_to_block(&mut runtime, 5);
+
+        assert_eq!(Pallet::vote(&mut runtime, alice, 0, true, u128::MAX - 10), Ok(()));
+        assert_eq!(Pallet::vote(&mut runtime, bob, 0, true, u128::MAX), Ok(()));
+        assert_eq!(runtime.democracy.referendum(0).unwrap().tally(), None);
+        assert!(!runtime.democracy.referendum(0).unwrap().is_passing());
+
+        run_to_block(&mut runtime, 10);
+        assert!(runtime.democracy.referendum(0).is_none());
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 0);
+    }
+}
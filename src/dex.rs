@@ -0,0 +1,577 @@
+use num::traits::{ CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Zero };
+use crate::{ assets, balances };
+use crate::metadata::ConstantMetadata;
+use crate::storage::{ Decode, Encode, StorageDoubleMap, StorageMap };
+use crate::support::{ Convert, DispatchResult, PalletId };
+
+pub trait Config: Sized +
+    crate::assets::Config +
+    AsMut<crate::balances::Pallet<Self>> +
+    AsMut<crate::assets::Pallet<Self>> +
+    AsMut<Pallet<Self>> {
+    /// Derives the account holding the reserves of all pools from `PALLET_ID`.
+    type PalletIdToAccountId: Convert<PalletId, Self::AccountId>;
+
+    /// The id the account of the pools is derived from.
+    const PALLET_ID: PalletId;
+    /// The fee taken from the amount going into a swap, in thousandths. For example 3 is 0.3%.
+    const LP_FEE: u32;
+}
+
+/// Either the native currency of `balances`, or an asset of `assets`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NativeOrAsset<AssetId> {
+    Native,
+    Asset(AssetId),
+}
+
+impl<AssetId: Encode> Encode for NativeOrAsset<AssetId> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            NativeOrAsset::Native => dest.push(0),
+            NativeOrAsset::Asset(id) => {
+                dest.push(1);
+                id.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<AssetId: Decode> Decode for NativeOrAsset<AssetId> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(NativeOrAsset::Native),
+            1 => Some(NativeOrAsset::Asset(AssetId::decode(input)?)),
+            _ => None,
+        }
+    }
+}
+
+/// A pool between the native currency and an asset. Liquidity providers own a share of the
+/// reserves of the pool, in proportion to their LP tokens.
+#[derive(Debug)]
+pub struct Pool<T: Config> {
+    pub native_reserve: T::Balance,
+    pub asset_reserve: T::Balance,
+    /// The total amount of LP tokens of the pool.
+    pub lp_supply: T::Balance,
+}
+
+impl<T: Config> Encode for Pool<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.native_reserve.encode_to(dest);
+        self.asset_reserve.encode_to(dest);
+        self.lp_supply.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pool<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let native_reserve = Decode::decode(input)?;
+        let asset_reserve = Decode::decode(input)?;
+        Some(Self { native_reserve, asset_reserve, lp_supply: Decode::decode(input)? })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The pool of each asset with the native currency.
+    pools: StorageMap<T::AssetId, Pool<T>>,
+    /// The LP tokens of each provider, for each pool. Zero balances are not stored.
+    lp_balances: StorageDoubleMap<T::AssetId, T::AccountId, T::Balance>,
+}
+
+impl<T: Config> Pallet<T> {
+
+    /// The account which holds the reserves of all pools.
+    pub fn account_id() -> T::AccountId {
+        T::PalletIdToAccountId::convert(T::PALLET_ID)
+    }
+
+    /// The pool between the native currency and `asset`.
+    pub fn pool(&self, asset: &T::AssetId) -> Option<Pool<T>> {
+        self.pools.get(asset)
+    }
+
+    /// The LP tokens of `who` in the pool of `asset`.
+    pub fn lp_balance(&self, asset: &T::AssetId, who: &T::AccountId) -> T::Balance {
+        self.lp_balances.get(asset, who).unwrap_or(T::Balance::zero())
+    }
+
+    fn set_lp_balance(&mut self, asset: T::AssetId, who: &T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.lp_balances.remove(&asset, who);
+        } else {
+            self.lp_balances.insert(&asset, who, &amount);
+        }
+    }
+
+    /// The amount out of a swap of `amount_in`, between pool reserves `reserve_in` and
+    /// `reserve_out`. The product of the reserves stays constant, after the fee is taken.
+    pub fn get_amount_out(
+        amount_in: T::Balance,
+        reserve_in: T::Balance,
+        reserve_out: T::Balance
+    ) -> Result<T::Balance, &'static str> {
+        if reserve_in.is_zero() || reserve_out.is_zero() {
+            return Err("Pool has no liquidity");
+        }
+        let amount_in_with_fee = amount_in
+            .checked_mul(&T::Balance::from(1000 - T::LP_FEE))
+            .ok_or("Overflow.")?;
+        let numerator = amount_in_with_fee.checked_mul(&reserve_out).ok_or("Overflow.")?;
+        let denominator = reserve_in
+            .checked_mul(&T::Balance::from(1000))
+            .and_then(|reserve_in| reserve_in.checked_add(&amount_in_with_fee))
+            .ok_or("Overflow.")?;
+        numerator.checked_div(&denominator).ok_or("Overflow.")
+    }
+
+    /// The amount into a swap which gives `amount_out`, like `get_amount_out` in reverse. This is
+    /// rounded up, in favor of the pool.
+    pub fn get_amount_in(
+        amount_out: T::Balance,
+        reserve_in: T::Balance,
+        reserve_out: T::Balance
+    ) -> Result<T::Balance, &'static str> {
+        if reserve_in.is_zero() || reserve_out.is_zero() {
+            return Err("Pool has no liquidity");
+        }
+        if amount_out >= reserve_out {
+            return Err("Not enough liquidity in the pool");
+        }
+        let numerator = reserve_in
+            .checked_mul(&amount_out)
+            .and_then(|product| product.checked_mul(&T::Balance::from(1000)))
+            .ok_or("Overflow.")?;
+        let denominator = reserve_out
+            .checked_sub(&amount_out)
+            .ok_or("Not enough liquidity in the pool")?
+            .checked_mul(&T::Balance::from(1000 - T::LP_FEE))
+            .ok_or("Overflow.")?;
+        numerator
+            .checked_div(&denominator)
+            .and_then(|amount_in| amount_in.checked_add(&T::Balance::from(1)))
+            .ok_or("Overflow.")
+    }
+
+    // Move `amount` of the native currency or of an asset from `from` to `to`.
+    fn move_funds(
+        runtime: &mut T,
+        currency: NativeOrAsset<T::AssetId>,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: T::Balance
+    ) -> DispatchResult {
+        match currency {
+            NativeOrAsset::Native => {
+                let balances: &mut balances::Pallet<T> = runtime.as_mut();
+                balances.transfer(from.clone(), to.clone(), amount)
+            }
+            NativeOrAsset::Asset(asset) => {
+                let assets: &mut assets::Pallet<T> = runtime.as_mut();
+                assets.do_transfer(asset, from, to, amount)
+            }
+        }
+    }
+
+    // Check that `move_funds` would succeed, without moving anything. Calls which move funds
+    // twice check both moves first, so a failing second move cannot leave the first one done.
+    fn ensure_can_move(
+        runtime: &mut T,
+        currency: NativeOrAsset<T::AssetId>,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: T::Balance
+    ) -> DispatchResult {
+        let (from_balance, to_balance, locked) = match currency {
+            NativeOrAsset::Native => {
+                let balances: &mut balances::Pallet<T> = runtime.as_mut();
+                (balances.balance(from), balances.balance(to), balances.locked(from))
+            }
+            NativeOrAsset::Asset(asset) => {
+                let assets: &mut assets::Pallet<T> = runtime.as_mut();
+                if assets.asset(&asset).is_none() {
+                    return Err("Asset does not exist");
+                }
+                (assets.balance(&asset, from), assets.balance(&asset, to), T::Balance::zero())
+            }
+        };
+        let new_from_balance = from_balance.checked_sub(&amount).ok_or("Not enough funds.")?;
+        if new_from_balance < locked {
+            return Err("Funds are locked.");
+        }
+        if from != to {
+            to_balance.checked_add(&amount).ok_or("Overflow.")?;
+        }
+        Ok(())
+    }
+
+    // Swap `amount_in` of `from` for `amount_out` of `to`, updating the reserves of the pool.
+    fn do_swap(
+        runtime: &mut T,
+        caller: &T::AccountId,
+        from: NativeOrAsset<T::AssetId>,
+        to: NativeOrAsset<T::AssetId>,
+        amount_in: T::Balance,
+        amount_out: T::Balance
+    ) -> DispatchResult {
+        let pool_account = Self::account_id();
+        Self::ensure_can_move(runtime, from, caller, &pool_account, amount_in)?;
+        Self::ensure_can_move(runtime, to, &pool_account, caller, amount_out)?;
+        Self::move_funds(runtime, from, caller, &pool_account, amount_in)?;
+        Self::move_funds(runtime, to, &pool_account, caller, amount_out)?;
+
+        let asset = swap_asset(from, to)?;
+        let dex: &mut Pallet<T> = runtime.as_mut();
+        let mut pool = dex.pool(&asset).ok_or("Pool does not exist")?;
+        let (reserve_in, reserve_out) = match from {
+            NativeOrAsset::Native => (&mut pool.native_reserve, &mut pool.asset_reserve),
+            NativeOrAsset::Asset(_) => (&mut pool.asset_reserve, &mut pool.native_reserve),
+        };
+        *reserve_in = reserve_in.checked_add(&amount_in).ok_or("Overflow.")?;
+        *reserve_out = reserve_out.checked_sub(&amount_out).ok_or("Not enough funds.")?;
+        dex.pools.insert(&asset, &pool);
+        Ok(())
+    }
+
+    // The reserves of the pool of a swap from `from` to `to`, as `(reserve_in, reserve_out)`.
+    fn swap_reserves(
+        &self,
+        from: NativeOrAsset<T::AssetId>,
+        to: NativeOrAsset<T::AssetId>
+    ) -> Result<(T::Balance, T::Balance), &'static str> {
+        let pool = self.pool(&swap_asset(from, to)?).ok_or("Pool does not exist")?;
+        match from {
+            NativeOrAsset::Native => Ok((pool.native_reserve, pool.asset_reserve)),
+            NativeOrAsset::Asset(_) => Ok((pool.asset_reserve, pool.native_reserve)),
+        }
+    }
+}
+
+// The asset of the pool a swap goes through. Pools are always between the native currency and an
+// asset.
+fn swap_asset<AssetId: Copy>(
+    from: NativeOrAsset<AssetId>,
+    to: NativeOrAsset<AssetId>
+) -> Result<AssetId, &'static str> {
+    match (from, to) {
+        (NativeOrAsset::Native, NativeOrAsset::Asset(asset)) => Ok(asset),
+        (NativeOrAsset::Asset(asset), NativeOrAsset::Native) => Ok(asset),
+        _ => Err("Swaps must be between the native currency and an asset"),
+    }
+}
+
+// `amount * numerator / denominator`, with checked operations.
+fn mul_div<T: Config>(
+    amount: T::Balance,
+    numerator: T::Balance,
+    denominator: T::Balance
+) -> Result<T::Balance, &'static str> {
+    amount
+        .checked_mul(&numerator)
+        .and_then(|product| product.checked_div(&denominator))
+        .ok_or("Overflow.")
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("PALLET_ID", T::PALLET_ID),
+            ConstantMetadata::new("LP_FEE", T::LP_FEE)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Create an empty pool between the native currency and `asset`.
+    #[call_index(0)]
+    pub fn create_pool(
+        runtime: &mut T,
+        _caller: T::AccountId,
+        asset: T::AssetId
+    ) -> DispatchResult {
+        let assets: &mut assets::Pallet<T> = runtime.as_mut();
+        if assets.asset(&asset).is_none() {
+            return Err("Asset does not exist");
+        }
+        let dex: &mut Pallet<T> = runtime.as_mut();
+        if dex.pool(&asset).is_some() {
+            return Err("Pool already exists");
+        }
+        let pool = Pool {
+            native_reserve: T::Balance::zero(),
+            asset_reserve: T::Balance::zero(),
+            lp_supply: T::Balance::zero(),
+        };
+        dex.pools.insert(&asset, &pool);
+        Ok(())
+    }
+
+    /// Add up to `native_desired` and `asset_desired` to the pool of `asset`, in the ratio of its
+    /// reserves, and get LP tokens for them. The first provider sets the ratio, and gets as many LP
+    /// tokens as the native currency it adds. Fails if less than `native_min` or `asset_min`
+    /// would be added.
+    #[call_index(1)]
+    pub fn add_liquidity(
+        runtime: &mut T,
+        caller: T::AccountId,
+        asset: T::AssetId,
+        native_desired: T::Balance,
+        asset_desired: T::Balance,
+        native_min: T::Balance,
+        asset_min: T::Balance
+    ) -> DispatchResult {
+        let dex: &mut Pallet<T> = runtime.as_mut();
+        let pool = dex.pool(&asset).ok_or("Pool does not exist")?;
+
+        let (native_amount, asset_amount, lp_amount) = if pool.lp_supply.is_zero() {
+            (native_desired, asset_desired, native_desired)
+        } else {
+            let (native_reserve, asset_reserve) = (pool.native_reserve, pool.asset_reserve);
+            let asset_optimal = mul_div::<T>(native_desired, asset_reserve, native_reserve)?;
+            let (native_amount, asset_amount) = if asset_optimal <= asset_desired {
+                (native_desired, asset_optimal)
+            } else {
+                let native_optimal = mul_div::<T>(asset_desired, native_reserve, asset_reserve)?;
+                (native_optimal, asset_desired)
+            };
+            // LP tokens are minted for the smaller share, which is the same for both amounts up to
+            // rounding.
+            let lp_amount = mul_div::<T>(native_amount, pool.lp_supply, native_reserve)?.min(
+                mul_div::<T>(asset_amount, pool.lp_supply, asset_reserve)?
+            );
+            (native_amount, asset_amount, lp_amount)
+        };
+        if native_amount < native_min || asset_amount < asset_min {
+            return Err("Slippage exceeds the limit");
+        }
+        if native_amount.is_zero() || asset_amount.is_zero() || lp_amount.is_zero() {
+            return Err("Not enough liquidity added");
+        }
+
+        let pool_account = Self::account_id();
+        let (native, currency) = (NativeOrAsset::Native, NativeOrAsset::Asset(asset));
+        Self::ensure_can_move(runtime, native, &caller, &pool_account, native_amount)?;
+        Self::ensure_can_move(runtime, currency, &caller, &pool_account, asset_amount)?;
+        Self::move_funds(runtime, native, &caller, &pool_account, native_amount)?;
+        Self::move_funds(runtime, currency, &caller, &pool_account, asset_amount)?;
+
+        let dex: &mut Pallet<T> = runtime.as_mut();
+        let new_lp_balance = dex
+            .lp_balance(&asset, &caller)
+            .checked_add(&lp_amount)
+            .ok_or("Overflow.")?;
+        let mut pool = dex.pool(&asset).ok_or("Pool does not exist")?;
+        pool.native_reserve = pool.native_reserve.checked_add(&native_amount).ok_or("Overflow.")?;
+        pool.asset_reserve = pool.asset_reserve.checked_add(&asset_amount).ok_or("Overflow.")?;
+        pool.lp_supply = pool.lp_supply.checked_add(&lp_amount).ok_or("Overflow.")?;
+        dex.set_lp_balance(asset, &caller, new_lp_balance);
+        dex.pools.insert(&asset, &pool);
+        Ok(())
+    }
+
+    /// Burn `lp_amount` of the LP tokens of the caller, and get back its share of the reserves of
+    /// the pool. Fails if less than `native_min` or `asset_min` would be returned.
+    #[call_index(2)]
+    pub fn remove_liquidity(
+        runtime: &mut T,
+        caller: T::AccountId,
+        asset: T::AssetId,
+        lp_amount: T::Balance,
+        native_min: T::Balance,
+        asset_min: T::Balance
+    ) -> DispatchResult {
+        let dex: &mut Pallet<T> = runtime.as_mut();
+        let new_lp_balance = dex
+            .lp_balance(&asset, &caller)
+            .checked_sub(&lp_amount)
+            .ok_or("Not enough LP tokens")?;
+        let pool = dex.pool(&asset).ok_or("Pool does not exist")?;
+        let native_amount = mul_div::<T>(lp_amount, pool.native_reserve, pool.lp_supply)?;
+        let asset_amount = mul_div::<T>(lp_amount, pool.asset_reserve, pool.lp_supply)?;
+        if native_amount < native_min || asset_amount < asset_min {
+            return Err("Slippage exceeds the limit");
+        }
+        let native_reserve = pool.native_reserve
+            .checked_sub(&native_amount)
+            .ok_or("Not enough liquidity in the pool")?;
+        let asset_reserve = pool.asset_reserve
+            .checked_sub(&asset_amount)
+            .ok_or("Not enough liquidity in the pool")?;
+        let lp_supply = pool.lp_supply.checked_sub(&lp_amount).ok_or("Not enough LP tokens")?;
+
+        let pool_account = Self::account_id();
+        let (native, currency) = (NativeOrAsset::Native, NativeOrAsset::Asset(asset));
+        Self::ensure_can_move(runtime, native, &pool_account, &caller, native_amount)?;
+        Self::ensure_can_move(runtime, currency, &pool_account, &caller, asset_amount)?;
+        Self::move_funds(runtime, native, &pool_account, &caller, native_amount)?;
+        Self::move_funds(runtime, currency, &pool_account, &caller, asset_amount)?;
+
+        let dex: &mut Pallet<T> = runtime.as_mut();
+        dex.set_lp_balance(asset, &caller, new_lp_balance);
+        dex.pools.insert(&asset, &Pool { native_reserve, asset_reserve, lp_supply });
+        Ok(())
+    }
+
+    /// Swap exactly `amount_in` of `from` for as much of `to` as the pool gives, which must be at
+    /// least `amount_out_min`.
+    #[call_index(3)]
+    pub fn swap_exact_in(
+        runtime: &mut T,
+        caller: T::AccountId,
+        from: NativeOrAsset<T::AssetId>,
+        to: NativeOrAsset<T::AssetId>,
+        amount_in: T::Balance,
+        amount_out_min: T::Balance
+    ) -> DispatchResult {
+        let dex: &mut Pallet<T> = runtime.as_mut();
+        let (reserve_in, reserve_out) = dex.swap_reserves(from, to)?;
+        let amount_out = Self::get_amount_out(amount_in, reserve_in, reserve_out)?;
+        if amount_out < amount_out_min {
+            return Err("Slippage exceeds the limit");
+        }
+        if amount_out.is_zero() {
+            return Err("Swap amount is too small");
+        }
+        Self::do_swap(runtime, &caller, from, to, amount_in, amount_out)
+    }
+
+    /// Swap as little of `from` as the pool needs for exactly `amount_out` of `to`, which must be
+    /// at most `amount_in_max`.
+    #[call_index(4)]
+    pub fn swap_exact_out(
+        runtime: &mut T,
+        caller: T::AccountId,
+        from: NativeOrAsset<T::AssetId>,
+        to: NativeOrAsset<T::AssetId>,
+        amount_out: T::Balance,
+        amount_in_max: T::Balance
+    ) -> DispatchResult {
+        let dex: &mut Pallet<T> = runtime.as_mut();
+        let (reserve_in, reserve_out) = dex.swap_reserves(from, to)?;
+        let amount_in = Self::get_amount_in(amount_out, reserve_in, reserve_out)?;
+        if amount_in > amount_in_max {
+            return Err("Slippage exceeds the limit");
+        }
+        Self::do_swap(runtime, &caller, from, to, amount_in, amount_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ NativeOrAsset, Pallet };
+    use crate::Runtime;
+
+    // A runtime where alice has 1_000 of the native currency and of asset 1, which has a pool.
+    fn new_runtime() -> Runtime {
+        let alice = "alice".to_string();
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 1_000);
+        assert_eq!(runtime.assets.create(alice.clone(), 1), Ok(()));
+        assert_eq!(runtime.assets.mint(alice.clone(), 1, alice.clone(), 1_000), Ok(()));
+        assert_eq!(Pallet::create_pool(&mut runtime, alice, 1), Ok(()));
+        runtime
+    }
+
+    #[test]
+    fn add_and_remove_liquidity() {
+        let alice = "alice".to_string();
+        let mut runtime = new_runtime();
+
+        assert_eq!(Pallet::create_pool(&mut runtime, alice.clone(), 1), Err("Pool already exists"));
+        assert_eq!(Pallet::add_liquidity(&mut runtime, alice.clone(), 1, 100, 400, 0, 0), Ok(()));
+        assert_eq!(runtime.dex.lp_balance(&1, &alice), 100);
+
+        // Later liquidity is added in the ratio of the reserves, 1 to 4.
+        assert_eq!(
+            Pallet::add_liquidity(&mut runtime, alice.clone(), 1, 50, 100, 0, 0),
+            Ok(())
+        );
+        let pool = runtime.dex.pool(&1).unwrap();
+        assert_eq!((pool.native_reserve, pool.asset_reserve, pool.lp_supply), (125, 500, 125));
+        assert_eq!(
+            Pallet::add_liquidity(&mut runtime, alice.clone(), 1, 50, 100, 30, 0),
+            Err("Slippage exceeds the limit")
+        );
+
+        assert_eq!(Pallet::remove_liquidity(&mut runtime, alice.clone(), 1, 25, 0, 0), Ok(()));
+        let pool = runtime.dex.pool(&1).unwrap();
+        assert_eq!((pool.native_reserve, pool.asset_reserve, pool.lp_supply), (100, 400, 100));
+        assert_eq!(runtime.balances.balance(&alice), 900);
+        assert_eq!(runtime.assets.balance(&1, &alice), 600);
+        assert_eq!(
+            Pallet::remove_liquidity(&mut runtime, alice, 1, 101, 0, 0),
+            Err("Not enough LP tokens")
+        );
+    }
+
+    #[test]
+    fn swaps() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let mut runtime = new_runtime();
+        runtime.balances.set_balance(&bob, 100);
+        assert_eq!(Pallet::add_liquidity(&mut runtime, alice, 1, 500, 500, 0, 0), Ok(()));
+
+        // With a fee of 0.3%, 100 in gives 500 * 99.7 / (500 + 99.7) = 83.1 out.
+        let (native, asset) = (NativeOrAsset::Native, NativeOrAsset::Asset(1));
+        assert_eq!(
+            Pallet::swap_exact_in(&mut runtime, bob.clone(), native, asset, 100, 84),
+            Err("Slippage exceeds the limit")
+        );
+        assert_eq!(
+            Pallet::swap_exact_in(&mut runtime, bob.clone(), native, asset, 100, 83),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&bob), 0);
+        assert_eq!(runtime.assets.balance(&1, &bob), 83);
+
+        // Swapping back for 50 needs 417 * 50 / (600 - 50) / 0.997 = 38.02 in, rounded up to 39.
+        assert_eq!(
+            Pallet::swap_exact_out(&mut runtime, bob.clone(), asset, native, 50, 38),
+            Err("Slippage exceeds the limit")
+        );
+        assert_eq!(
+            Pallet::swap_exact_out(&mut runtime, bob.clone(), asset, native, 50, 39),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&bob), 50);
+        assert_eq!(runtime.assets.balance(&1, &bob), 44);
+        let pool = runtime.dex.pool(&1).unwrap();
+        assert_eq!((pool.native_reserve, pool.asset_reserve), (550, 456));
+
+        assert_eq!(
+            Pallet::swap_exact_in(&mut runtime, bob, native, native, 10, 0),
+            Err("Swaps must be between the native currency and an asset")
+        );
+    }
+
+    #[test]
+    fn failed_moves_leave_funds_untouched() {
+        let alice = "alice".to_string();
+        let mut runtime = new_runtime();
+        assert_eq!(Pallet::add_liquidity(&mut runtime, alice.clone(), 1, 500, 500, 0, 0), Ok(()));
+
+        // The native reserve cannot leave the pool, so a swap into it fails without taking the
+        // asset from alice.
+        let pool_account = Pallet::<Runtime>::account_id();
+        runtime.balances.set_lock(*b"lock    ", &pool_account, 500);
+        let (native, asset) = (NativeOrAsset::Native, NativeOrAsset::Asset(1));
+        assert_eq!(
+            Pallet::swap_exact_in(&mut runtime, alice.clone(), asset, native, 100, 0),
+            Err("Funds are locked.")
+        );
+        assert_eq!(runtime.assets.balance(&1, &alice), 500);
+        assert_eq!(
+            Pallet::remove_liquidity(&mut runtime, alice.clone(), 1, 100, 0, 0),
+            Err("Funds are locked.")
+        );
+        assert_eq!(runtime.assets.balance(&1, &alice), 500);
+        assert_eq!(runtime.dex.lp_balance(&1, &alice), 500);
+    }
+}
@@ -0,0 +1,214 @@
+use num::traits::Zero;
+use crate::balances;
+use crate::storage::{ Decode, Encode, StorageMap, StorageValue };
+use crate::support::{ DispatchResult, EnsureOrigin, Origin, Weight };
+
+/// The index of an escrow.
+pub type EscrowId = u32;
+
+pub trait Config: Sized +
+    crate::balances::Config +
+    AsMut<crate::system::Pallet<Self>> +
+    AsMut<crate::balances::Pallet<Self>> +
+    AsMut<Pallet<Self>> {
+    /// The origin which can release any escrow, on top of its payer.
+    type ArbiterOrigin: EnsureOrigin<Self::AccountId>;
+}
+
+/// Funds reserved from `payer`, which go to `beneficiary` when released, or back to `payer` once
+/// `deadline` is reached.
+#[derive(Debug)]
+pub struct Escrow<T: Config> {
+    pub payer: T::AccountId,
+    pub beneficiary: T::AccountId,
+    pub amount: T::Balance,
+    pub deadline: T::BlockNumber,
+}
+
+impl<T: Config> Encode for Escrow<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.payer.encode_to(dest);
+        self.beneficiary.encode_to(dest);
+        self.amount.encode_to(dest);
+        self.deadline.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Escrow<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            payer: Decode::decode(input)?,
+            beneficiary: Decode::decode(input)?,
+            amount: Decode::decode(input)?,
+            deadline: Decode::decode(input)?,
+        })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The escrows which were not released or refunded yet.
+    escrows: StorageMap<EscrowId, Escrow<T>>,
+    /// The id of the next escrow to be opened.
+    next_escrow_id: StorageValue<EscrowId>,
+    /// The escrows to refund at each block.
+    deadlines: StorageMap<T::BlockNumber, Vec<EscrowId>>,
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn escrow(&self, id: EscrowId) -> Option<Escrow<T>> {
+        self.escrows.get(&id)
+    }
+
+    // Remove an escrow, and its entry in `deadlines`.
+    fn take_escrow(&mut self, id: EscrowId) -> Option<Escrow<T>> {
+        let escrow = self.escrow(id)?;
+        self.escrows.remove(&id);
+        let mut ids = self.deadlines.get(&escrow.deadline).unwrap_or_default();
+        ids.retain(|other| *other != id);
+        if ids.is_empty() {
+            self.deadlines.remove(&escrow.deadline);
+        } else {
+            self.deadlines.insert(&escrow.deadline, &ids);
+        }
+        Some(escrow)
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {
+    // Refund the escrows which reach their deadline in this block.
+    fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) -> Weight {
+        let escrow: &mut Pallet<T> = runtime.as_mut();
+        let ids = escrow.deadlines.get(&block_number).unwrap_or_default();
+        escrow.deadlines.remove(&block_number);
+        let mut escrows = Vec::new();
+        for id in ids {
+            escrows.extend(escrow.escrow(id));
+            escrow.escrows.remove(&id);
+        }
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        for Escrow { payer, amount, .. } in escrows {
+            balances.unreserve(&payer, amount);
+        }
+        0
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Reserve `amount` from the caller for `beneficiary`, until the escrow is released or
+    /// `deadline` is reached.
+    #[call_index(0)]
+    pub fn open(
+        runtime: &mut T,
+        caller: T::AccountId,
+        beneficiary: T::AccountId,
+        amount: T::Balance,
+        deadline: T::BlockNumber
+    ) -> DispatchResult {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        if deadline <= system.block_number() {
+            return Err("The deadline must be in the future");
+        }
+        if amount.is_zero() {
+            return Err("Cannot open an escrow of zero");
+        }
+        let escrow: &mut Pallet<T> = runtime.as_mut();
+        let id = escrow.next_escrow_id.get().unwrap_or(0);
+        let next_escrow_id = id.checked_add(1).ok_or("Overflow")?;
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.reserve(&caller, amount)?;
+
+        let escrow: &mut Pallet<T> = runtime.as_mut();
+        escrow.next_escrow_id.put(&next_escrow_id);
+        escrow.escrows.insert(&id, &Escrow { payer: caller, beneficiary, amount, deadline });
+        let mut ids = escrow.deadlines.get(&deadline).unwrap_or_default();
+        ids.push(id);
+        escrow.deadlines.insert(&deadline, &ids);
+        Ok(())
+    }
+
+    /// Pay the funds of an escrow to its beneficiary. Only the payer or the arbiter can release.
+    #[call_index(1)]
+    pub fn release(runtime: &mut T, origin: Origin<T::AccountId>, id: EscrowId) -> DispatchResult {
+        let escrow: &mut Pallet<T> = runtime.as_mut();
+        let details = escrow.escrow(id).ok_or("Escrow does not exist")?;
+        if !matches!(&origin, Origin::Signed(who) if *who == details.payer) {
+            T::ArbiterOrigin::ensure_origin(origin)
+                .map_err(|_| "Only the payer or the arbiter can release this escrow")?;
+        }
+        let Some(Escrow { payer, beneficiary, amount, .. }) = escrow.take_escrow(id) else {
+            return Err("Escrow does not exist");
+        };
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.repatriate_reserved(&payer, &beneficiary, amount)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pallet;
+    use crate::{ support::Origin, testing::next_block, Runtime };
+
+    #[test]
+    fn release_escrow() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(
+            Pallet::open(&mut runtime, alice.clone(), bob.clone(), 30, 0),
+            Err("The deadline must be in the future")
+        );
+        assert_eq!(Pallet::open(&mut runtime, alice.clone(), bob.clone(), 30, 5), Ok(()));
+        assert_eq!(Pallet::open(&mut runtime, alice.clone(), bob.clone(), 20, 5), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 50);
+
+        // The beneficiary cannot release the funds itself.
+        assert_eq!(
+            Pallet::release(&mut runtime, Origin::Signed(bob.clone()), 0),
+            Err("Only the payer or the arbiter can release this escrow")
+        );
+        assert_eq!(Pallet::release(&mut runtime, Origin::Signed(alice.clone()), 0), Ok(()));
+        assert_eq!(Pallet::release(&mut runtime, Origin::Root, 1), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 50);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(
+            Pallet::release(&mut runtime, Origin::Root, 1),
+            Err("Escrow does not exist")
+        );
+
+        // Nothing is reserved when there are no escrow ids left.
+        runtime.escrow.next_escrow_id.put(&u32::MAX);
+        assert_eq!(Pallet::open(&mut runtime, alice.clone(), bob, 30, 5), Err("Overflow"));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+    }
+
+    #[test]
+    fn refund_after_deadline() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        assert_eq!(Pallet::open(&mut runtime, alice.clone(), bob.clone(), 30, 2), Ok(()));
+
+        next_block(&mut runtime);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 30);
+        next_block(&mut runtime);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert!(runtime.escrow.escrow(0).is_none());
+        assert_eq!(
+            Pallet::release(&mut runtime, Origin::Signed(alice), 0),
+            Err("Escrow does not exist")
+        );
+    }
+}
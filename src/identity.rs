@@ -0,0 +1,410 @@
+use num::traits::{ Saturating, Zero };
+use crate::balances;
+use crate::metadata::ConstantMetadata;
+use crate::storage::{ Decode, Encode, StorageMap, StorageValue };
+use crate::support::{ DispatchResult, EnsureOrigin, Origin };
+
+/// The index of a registrar.
+pub type RegistrarIndex = u32;
+
+pub trait Config: Sized +
+    crate::balances::Config +
+    AsMut<crate::balances::Pallet<Self>> +
+    AsMut<Pallet<Self>> {
+    /// The origin which can add registrars.
+    type RegistrarOrigin: EnsureOrigin<Self::AccountId>;
+
+    /// The deposit reserved for setting an identity.
+    const BASIC_DEPOSIT: Self::Balance;
+    /// The deposit reserved for each sub-account.
+    const SUB_ACCOUNT_DEPOSIT: Self::Balance;
+    /// The maximum number of sub-accounts of an identity.
+    const MAX_SUB_ACCOUNTS: usize;
+}
+
+/// The information an account gives about itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentityInfo {
+    /// The name shown for the account.
+    pub display: String,
+    pub email: Option<String>,
+    pub web: Option<String>,
+}
+
+/// The opinion of a registrar about an identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+    /// The registrar does not know whether the identity is right.
+    Unknown,
+    /// The identity looks right, but was not checked in depth.
+    Reasonable,
+    /// The identity was checked and is right.
+    KnownGood,
+    /// The identity is wrong.
+    Erroneous,
+}
+
+impl Encode for IdentityInfo {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.display.encode_to(dest);
+        self.email.encode_to(dest);
+        self.web.encode_to(dest);
+    }
+}
+
+impl Decode for IdentityInfo {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let display = Decode::decode(input)?;
+        let email = Decode::decode(input)?;
+        Some(Self { display, email, web: Decode::decode(input)? })
+    }
+}
+
+impl Encode for Judgement {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(*self as u8);
+    }
+}
+
+impl Decode for Judgement {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(Judgement::Unknown),
+            1 => Some(Judgement::Reasonable),
+            2 => Some(Judgement::KnownGood),
+            3 => Some(Judgement::Erroneous),
+            _ => None,
+        }
+    }
+}
+
+/// An identity, with the deposit reserved for it and the judgements it received.
+#[derive(Debug)]
+pub struct Registration<T: Config> {
+    pub info: IdentityInfo,
+    pub deposit: T::Balance,
+    pub judgements: Vec<(RegistrarIndex, Judgement)>,
+}
+
+impl<T: Config> Encode for Registration<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.info.encode_to(dest);
+        self.deposit.encode_to(dest);
+        self.judgements.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Registration<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let info = Decode::decode(input)?;
+        let deposit = Decode::decode(input)?;
+        Some(Self { info, deposit, judgements: Decode::decode(input)? })
+    }
+}
+
+/// A sub-account of an identity, with its own name under the parent.
+#[derive(Debug)]
+pub struct SubAccount<T: Config> {
+    pub parent: T::AccountId,
+    pub name: String,
+}
+
+impl<T: Config> Encode for SubAccount<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.parent.encode_to(dest);
+        self.name.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for SubAccount<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let parent = Decode::decode(input)?;
+        Some(Self { parent, name: Decode::decode(input)? })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The identity set by each account.
+    identities: StorageMap<T::AccountId, Registration<T>>,
+    /// The accounts which can give judgements, by their index.
+    registrars: StorageValue<Vec<T::AccountId>>,
+    /// The sub-accounts of each parent.
+    subs_of: StorageMap<T::AccountId, Vec<T::AccountId>>,
+    /// The parent of each sub-account.
+    super_of: StorageMap<T::AccountId, SubAccount<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// The identity set by `who`.
+    pub fn identity(&self, who: &T::AccountId) -> Option<Registration<T>> {
+        self.identities.get(who)
+    }
+
+    /// The parent of `who`, if it is a sub-account.
+    pub fn super_of(&self, who: &T::AccountId) -> Option<SubAccount<T>> {
+        self.super_of.get(who)
+    }
+
+    /// The sub-accounts of `who`.
+    pub fn subs_of(&self, who: &T::AccountId) -> Vec<T::AccountId> {
+        self.subs_of.get(who).unwrap_or_default()
+    }
+
+    pub fn registrars(&self) -> Vec<T::AccountId> {
+        self.registrars.get().unwrap_or_default()
+    }
+
+    /// The human-readable name of `who`: the display name of its identity, or the display name of
+    /// its parent followed by its own name for a sub-account.
+    pub fn display_name(&self, who: &T::AccountId) -> Option<String> {
+        if let Some(registration) = self.identity(who) {
+            return Some(registration.info.display.clone());
+        }
+        let sub = self.super_of(who)?;
+        let parent = self.identity(&sub.parent)?;
+        Some(format!("{}/{}", parent.info.display, sub.name))
+    }
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("BASIC_DEPOSIT", T::BASIC_DEPOSIT),
+            ConstantMetadata::new("SUB_ACCOUNT_DEPOSIT", T::SUB_ACCOUNT_DEPOSIT),
+            ConstantMetadata::new("MAX_SUB_ACCOUNTS", T::MAX_SUB_ACCOUNTS)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Set the identity of the caller, reserving `BASIC_DEPOSIT` the first time. Previous
+    /// judgements are cleared, since they were about the previous information.
+    #[call_index(0)]
+    pub fn set_identity(
+        runtime: &mut T,
+        caller: T::AccountId,
+        info: IdentityInfo
+    ) -> DispatchResult {
+        let identity: &mut Pallet<T> = runtime.as_mut();
+        if identity.super_of(&caller).is_some() {
+            return Err("A sub-account cannot have an identity");
+        }
+        if let Some(mut registration) = identity.identity(&caller) {
+            registration.info = info;
+            registration.judgements.clear();
+            identity.identities.insert(&caller, &registration);
+            return Ok(());
+        }
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.reserve(&caller, T::BASIC_DEPOSIT)?;
+
+        let identity: &mut Pallet<T> = runtime.as_mut();
+        let registration = Registration { info, deposit: T::BASIC_DEPOSIT, judgements: vec![] };
+        identity.identities.insert(&caller, &registration);
+        Ok(())
+    }
+
+    /// Clear the identity of the caller and remove its sub-accounts, returning all deposits.
+    #[call_index(1)]
+    pub fn clear_identity(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+        let identity: &mut Pallet<T> = runtime.as_mut();
+        let registration = identity.identity(&caller).ok_or("Identity does not exist")?;
+        let subs = identity.subs_of(&caller);
+        identity.identities.remove(&caller);
+        identity.subs_of.remove(&caller);
+        for sub in &subs {
+            identity.super_of.remove(sub);
+        }
+
+        // Only what is reserved can be unreserved, so saturating the deposits frees all of it.
+        let sub_deposits = (0..subs.len()).fold(T::Balance::zero(), |total, _| {
+            total.saturating_add(T::SUB_ACCOUNT_DEPOSIT)
+        });
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.unreserve(&caller, registration.deposit.saturating_add(sub_deposits));
+        Ok(())
+    }
+
+    /// Add `account` as a new registrar, which can give judgements on identities.
+    #[call_index(2)]
+    pub fn add_registrar(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        account: T::AccountId
+    ) -> DispatchResult {
+        T::RegistrarOrigin::ensure_origin(origin)?;
+        let mut registrars = self.registrars();
+        registrars.push(account);
+        self.registrars.put(&registrars);
+        Ok(())
+    }
+
+    /// Give a judgement on the identity of `target`, as the registrar at `reg_index`.
+    #[call_index(3)]
+    pub fn provide_judgement(
+        &mut self,
+        caller: T::AccountId,
+        reg_index: RegistrarIndex,
+        target: T::AccountId,
+        judgement: Judgement
+    ) -> DispatchResult {
+        if self.registrars().get(reg_index as usize) != Some(&caller) {
+            return Err("You are not this registrar");
+        }
+        let mut registration = self.identity(&target).ok_or("Identity does not exist")?;
+        match registration.judgements.iter_mut().find(|(index, _)| *index == reg_index) {
+            Some((_, previous)) => *previous = judgement,
+            None => registration.judgements.push((reg_index, judgement)),
+        }
+        self.identities.insert(&target, &registration);
+        Ok(())
+    }
+
+    /// Register `sub` as a sub-account of the identity of the caller, named `name`. This reserves
+    /// `SUB_ACCOUNT_DEPOSIT` from the caller.
+    #[call_index(4)]
+    pub fn add_sub(
+        runtime: &mut T,
+        caller: T::AccountId,
+        sub: T::AccountId,
+        name: String
+    ) -> DispatchResult {
+        let identity: &mut Pallet<T> = runtime.as_mut();
+        if identity.identity(&caller).is_none() {
+            return Err("Identity does not exist");
+        }
+        if sub == caller || identity.identity(&sub).is_some() {
+            return Err("This account already has an identity");
+        }
+        if identity.super_of(&sub).is_some() {
+            return Err("This account is already a sub-account");
+        }
+        if identity.subs_of(&caller).len() >= T::MAX_SUB_ACCOUNTS {
+            return Err("Too many sub-accounts");
+        }
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.reserve(&caller, T::SUB_ACCOUNT_DEPOSIT)?;
+
+        let identity: &mut Pallet<T> = runtime.as_mut();
+        let mut subs = identity.subs_of(&caller);
+        subs.push(sub.clone());
+        identity.subs_of.insert(&caller, &subs);
+        identity.super_of.insert(&sub, &SubAccount { parent: caller, name });
+        Ok(())
+    }
+
+    /// Remove `sub` from the sub-accounts of the caller, returning its deposit.
+    #[call_index(5)]
+    pub fn remove_sub(runtime: &mut T, caller: T::AccountId, sub: T::AccountId) -> DispatchResult {
+        let identity: &mut Pallet<T> = runtime.as_mut();
+        if identity.super_of(&sub).is_none_or(|sub| sub.parent != caller) {
+            return Err("Not a sub-account of the caller");
+        }
+        identity.super_of.remove(&sub);
+        let mut subs = identity.subs_of(&caller);
+        subs.retain(|account| *account != sub);
+        if subs.is_empty() {
+            identity.subs_of.remove(&caller);
+        } else {
+            identity.subs_of.insert(&caller, &subs);
+        }
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.unreserve(&caller, T::SUB_ACCOUNT_DEPOSIT);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ IdentityInfo, Judgement, Pallet };
+    use crate::{ support::Origin, Runtime };
+
+    fn info(display: &str) -> IdentityInfo {
+        IdentityInfo { display: display.to_string(), email: None, web: None }
+    }
+
+    #[test]
+    fn set_identity_and_judgement() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(Pallet::set_identity(&mut runtime, alice.clone(), info("Alice")), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+        assert_eq!(runtime.identity.display_name(&alice), Some("Alice".to_string()));
+
+        assert_eq!(
+            runtime.identity.add_registrar(Origin::Signed(bob.clone()), bob.clone()),
+            Err("Expected the root origin")
+        );
+        assert_eq!(runtime.identity.add_registrar(Origin::Root, bob.clone()), Ok(()));
+        // Only the registrar itself can give its judgements.
+        let judgement = Judgement::KnownGood;
+        assert_eq!(
+            runtime.identity.provide_judgement(alice.clone(), 0, alice.clone(), judgement),
+            Err("You are not this registrar")
+        );
+        assert_eq!(
+            runtime.identity.provide_judgement(bob.clone(), 0, alice.clone(), Judgement::KnownGood),
+            Ok(())
+        );
+        assert_eq!(
+            runtime.identity.identity(&alice).unwrap().judgements,
+            vec![(0, Judgement::KnownGood)]
+        );
+
+        // Changing the identity keeps the deposit, but clears judgements.
+        assert_eq!(Pallet::set_identity(&mut runtime, alice.clone(), info("Alicia")), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+        assert!(runtime.identity.identity(&alice).unwrap().judgements.is_empty());
+
+        assert_eq!(Pallet::clear_identity(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.identity.display_name(&alice), None);
+        assert_eq!(runtime.balances.balance(&alice), 100);
+    }
+
+    #[test]
+    fn sub_accounts() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let charlie = "charlie".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(
+            Pallet::add_sub(&mut runtime, alice.clone(), bob.clone(), "savings".to_string()),
+            Err("Identity does not exist")
+        );
+        assert_eq!(Pallet::set_identity(&mut runtime, alice.clone(), info("Alice")), Ok(()));
+        assert_eq!(
+            Pallet::add_sub(&mut runtime, alice.clone(), bob.clone(), "savings".to_string()),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::add_sub(&mut runtime, alice.clone(), charlie.clone(), "cold".to_string()),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.reserved_balance(&alice), 14);
+        assert_eq!(runtime.identity.display_name(&bob), Some("Alice/savings".to_string()));
+
+        assert_eq!(Pallet::remove_sub(&mut runtime, alice.clone(), bob.clone()), Ok(()));
+        assert_eq!(runtime.identity.display_name(&bob), None);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 12);
+
+        // Clearing the identity removes the remaining sub-accounts, and returns all deposits.
+        assert_eq!(Pallet::clear_identity(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.identity.super_of(&charlie).map(|sub| sub.parent), None);
+        assert_eq!(runtime.balances.balance(&alice), 100);
+    }
+}
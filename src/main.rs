@@ -1,7 +1,22 @@
+mod assets;
 mod balances;
+mod democracy;
+mod dex;
+mod escrow;
+mod identity;
+mod metadata;
+mod nfts;
 mod system;
 mod support;
+mod timestamp;
 mod proof_of_existence;
+mod proxy;
+mod scheduler;
+mod staking;
+mod storage;
+mod treasury;
+mod tx_pause;
+mod vesting;
 
 use crate::support::Dispatch;
 
@@ -10,82 +25,317 @@ pub mod types {
     pub type Balance = u128;
     pub type BlockNumber = u32;
     pub type Nonce = u32;
+    pub type Moment = u64;
     pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall>;
     pub type Header = crate::support::Header<BlockNumber>;
     pub type Block = crate::support::Block<Header, Extrinsic>;
-    pub type Content = &'static str;
+    pub type Content = String;
+    pub type CollectionId = u32;
+    pub type ItemId = u32;
+    pub type AssetId = u32;
 }
 
 #[macros::runtime]
 #[derive(Debug)]
 pub struct Runtime {
+    #[runtime::system]
     system: system::Pallet<Self>,
+    #[pallet_index(1)]
+    #[inherent]
+    timestamp: timestamp::Pallet<Self>,
+    #[pallet_index(2)]
     balances: balances::Pallet<Self>,
+    #[pallet_index(3)]
     proof_of_existence: proof_of_existence::Pallet<Self>,
+    #[pallet_index(4)]
+    proxy: proxy::Pallet<Self>,
+    #[pallet_index(5)]
+    scheduler: scheduler::Pallet<Self>,
+    #[pallet_index(6)]
+    vesting: vesting::Pallet<Self>,
+    #[pallet_index(7)]
+    staking: staking::Pallet<Self>,
+    #[pallet_index(8)]
+    democracy: democracy::Pallet<Self>,
+    #[pallet_index(9)]
+    treasury: treasury::Pallet<Self>,
+    #[pallet_index(10)]
+    identity: identity::Pallet<Self>,
+    #[pallet_index(11)]
+    nfts: nfts::Pallet<Self>,
+    #[pallet_index(12)]
+    escrow: escrow::Pallet<Self>,
+    #[pallet_index(13)]
+    assets: assets::Pallet<Self>,
+    #[pallet_index(14)]
+    dex: dex::Pallet<Self>,
+    #[pallet_index(15)]
+    credits: balances::Pallet<Self, support::Instance2>,
+    #[pallet_index(16)]
+    tx_pause: tx_pause::Pallet<Self>,
 }
 
 impl system::Config for Runtime {
     type AccountId = types::AccountId;
     type BlockNumber = types::BlockNumber;
     type Nonce = types::Nonce;
+    type BaseCallFilter = tx_pause::Pallet<Self>;
+
+    const MAX_BLOCK_WEIGHT: support::Weight = 1_000;
+    const EXTRINSIC_WEIGHT: support::Weight = 10;
+}
+
+impl timestamp::Config for Runtime {
+    type Moment = types::Moment;
+
+    const MINIMUM_PERIOD: types::Moment = 3_000;
+    const MAX_DRIFT: types::Moment = 30_000;
 }
 
 impl balances::Config for Runtime {
     type Balance = types::Balance;
 }
 
+// A second ledger of credits, with its own storage and calls.
+impl balances::Config<support::Instance2> for Runtime {
+    type Balance = types::Balance;
+}
+
 impl proof_of_existence::Config for Runtime {
     type Content = types::Content;
 }
 
+impl proxy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type ProxyType = ProxyType;
+}
+
+impl scheduler::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+}
+
+impl vesting::Config for Runtime {
+    type BlockNumberToBalance = support::ConvertInto;
+
+    const MAX_VESTING_SCHEDULES: usize = 3;
+}
+
+impl staking::Config for Runtime {
+    const BLOCKS_PER_ERA: types::BlockNumber = 5;
+    const VALIDATOR_COUNT: usize = 2;
+    const BONDING_DURATION: staking::EraIndex = 2;
+    const REWARD_PER_VALIDATOR: types::Balance = 10;
+}
+
+impl democracy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+
+    const MINIMUM_DEPOSIT: types::Balance = 10;
+    const LAUNCH_PERIOD: types::BlockNumber = 5;
+    const VOTING_PERIOD: types::BlockNumber = 3;
+    const ENACTMENT_DELAY: types::BlockNumber = 2;
+}
+
+impl treasury::Config for Runtime {
+    type PalletIdToAccountId = support::ConvertInto;
+    type ApproveOrigin = support::EnsureRoot;
+
+    const PALLET_ID: support::PalletId = "py/trsry";
+    const PROPOSAL_BOND_PERCENT: u32 = 5;
+    const PROPOSAL_BOND_MINIMUM: types::Balance = 1;
+    const SPEND_PERIOD: types::BlockNumber = 4;
+    const BURN_PERCENT: u32 = 10;
+}
+
+impl identity::Config for Runtime {
+    type RegistrarOrigin = support::EnsureRoot;
+
+    const BASIC_DEPOSIT: types::Balance = 10;
+    const SUB_ACCOUNT_DEPOSIT: types::Balance = 2;
+    const MAX_SUB_ACCOUNTS: usize = 3;
+}
+
+impl nfts::Config for Runtime {
+    type CollectionId = types::CollectionId;
+    type ItemId = types::ItemId;
+
+    const COLLECTION_DEPOSIT: types::Balance = 10;
+}
+
+impl escrow::Config for Runtime {
+    type ArbiterOrigin = support::EnsureRoot;
+}
+
+impl tx_pause::Config for Runtime {
+    type PauseOrigin = support::EnsureRoot;
+
+    const UNPAUSABLE_PALLETS: &'static [&'static str] = &["system", "timestamp", "tx_pause"];
+}
+
+impl assets::Config for Runtime {
+    type AssetId = types::AssetId;
+}
+
+impl dex::Config for Runtime {
+    type PalletIdToAccountId = support::ConvertInto;
+
+    const PALLET_ID: support::PalletId = "py/dex";
+    const LP_FEE: u32 = 3;
+}
+
+/// The kinds of proxies an account can authorize, each allowing a subset of `RuntimeCall`s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProxyType {
+    /// Allows all calls.
+    Any,
+    /// Allows only `balances::transfer`.
+    Transfer,
+    /// Allows only `proof_of_existence` calls.
+    ProofOfExistence,
+}
+
+impl storage::Encode for ProxyType {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(self.clone() as u8);
+    }
+}
+
+impl storage::Decode for ProxyType {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(ProxyType::Any),
+            1 => Some(ProxyType::Transfer),
+            2 => Some(ProxyType::ProofOfExistence),
+            _ => None,
+        }
+    }
+}
+
+impl support::InstanceFilter<RuntimeCall> for ProxyType {
+    fn filter(&self, call: &RuntimeCall) -> bool {
+        match self {
+            ProxyType::Any => true,
+            ProxyType::Transfer =>
+                matches!(call, RuntimeCall::balances(balances::Call::transfer { .. })),
+            ProxyType::ProofOfExistence => matches!(call, RuntimeCall::proof_of_existence(_)),
+        }
+    }
+}
+
 fn main() {
     let alice = "alice".to_string();
     let bob = "bob".to_string();
     let charlie = "charlie".to_string();
 
-    let mut runtime = Runtime::new();
+    // Pass `--metadata` to print the metadata of the runtime as JSON, instead of running it.
+    if std::env::args().nth(1).as_deref() == Some("--metadata") {
+        println!("{}", Runtime::metadata().to_json());
+        return;
+    }
+
+    // Pass a path to keep the state of the runtime in a file, so the next run resumes from it.
+    let store = match std::env::args().nth(1) {
+        Some(path) => {
+            let backend = storage::FileBackend::open(path).expect("cannot open the store");
+            storage::Store::with_backend(backend)
+        }
+        None => storage::Store::new(),
+    };
+    let mut runtime = Runtime::with_store(store);
+    // A resumed runtime continues from its last block and time, with blocks six seconds apart.
+    let block_number = runtime.system.block_number();
+    let now: types::Moment = runtime.timestamp.now();
 
-    runtime.balances.set_balance(&alice, 100);
+    // The genesis balances are only set when starting a new chain.
+    if block_number == 0 {
+        runtime.balances.set_balance(&alice, 100);
+    }
 
+    // The block author creates the inherents of a block, like its timestamp, from its own data.
+    let mut inherent_data = support::InherentData::new();
+    inherent_data.put_data(timestamp::INHERENT_IDENTIFIER, now + 6_000);
+    let mut extrinsics = Runtime::create_inherents(&inherent_data);
+    extrinsics.extend(vec![
+        types::Extrinsic::signed(
+            alice.clone(),
+            balances::Call::<Runtime>::new_transfer(bob.clone(), 30)
+        ),
+        types::Extrinsic::signed(
+            alice.clone(),
+            balances::Call::<Runtime>::new_transfer(charlie.clone(), 20)
+        )
+    ]);
     let block_1 = types::Block {
-        header: support::Header { block_number: 1 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer {
-                    to: bob.clone(),
-                    amount: 30,
-                }),
-            },
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer {
-                    to: charlie.clone(),
-                    amount: 20,
-                }),
-            }
-        ],
+        header: support::Header { block_number: block_number + 1 },
+        extrinsics,
     };
+    // Other nodes check those inherents against their own data before executing the block.
+    Runtime::check_inherents(&block_1, &inherent_data).expect("invalid inherents");
     runtime.execute_block(block_1).expect("invalid block");
 
     let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
+        header: support::Header { block_number: block_number + 2 },
         extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "Hellow World!",
-                }),
-            },
-            support::Extrinsic {
-                caller: bob.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
-                    claim: "False claim",
-                }),
-            }
+            types::Extrinsic::unsigned(timestamp::Call::new_set(now + 12_000)),
+            types::Extrinsic::signed(
+                alice.clone(),
+                proof_of_existence::Call::new_create_claim("Hellow World!".to_string())
+            ),
+            types::Extrinsic::signed(
+                bob.clone(),
+                proof_of_existence::Call::new_revoke_claim("False claim".to_string())
+            )
         ],
     };
     runtime.execute_block(block_2).expect("invalid block");
 
+    let block_3 = types::Block {
+        header: support::Header { block_number: block_number + 3 },
+        extrinsics: vec![
+            types::Extrinsic::unsigned(timestamp::Call::new_set(now + 18_000)),
+            types::Extrinsic::signed(
+                alice.clone(),
+                proxy::Call::new_add_proxy(bob.clone(), ProxyType::Transfer, 0)
+            ),
+            types::Extrinsic::signed(
+                bob.clone(),
+                proxy::Call::new_proxy(
+                    alice.clone(),
+                    Box::new(balances::Call::<Runtime>::new_transfer(charlie.clone(), 10).into())
+                )
+            )
+        ],
+    };
+    runtime.execute_block(block_3).expect("invalid block");
+
     println!("{:#?}", runtime);
 }
+
+/// Helpers for the tests of the pallets, which run the runtime through real blocks.
+#[cfg(test)]
+mod testing {
+    use crate::{ support, timestamp, types, Runtime };
+
+    /// A block at `block_number` with the inherents for time `now`, followed by `extrinsics`.
+    pub fn block(block_number: u32, now: u64, extrinsics: Vec<types::Extrinsic>) -> types::Block {
+        let mut data = support::InherentData::new();
+        data.put_data(timestamp::INHERENT_IDENTIFIER, now);
+        let mut inherents = Runtime::create_inherents(&data);
+        inherents.extend(extrinsics);
+        types::Block { header: support::Header { block_number }, extrinsics: inherents }
+    }
+
+    /// Execute empty blocks, six seconds apart, until the runtime is at block `n`.
+    pub fn run_to_block(runtime: &mut Runtime, n: u32) {
+        while runtime.system.block_number() < n {
+            let block_number = runtime.system.block_number() + 1;
+            let block = block(block_number, block_number as u64 * 6_000, vec![]);
+            runtime.execute_block(block).expect("invalid block");
+        }
+    }
+
+    /// Execute one empty block.
+    pub fn next_block(runtime: &mut Runtime) {
+        run_to_block(runtime, runtime.system.block_number() + 1);
+    }
+}
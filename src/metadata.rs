@@ -0,0 +1,483 @@
+/// A description of the pallets of a runtime, so that clients can discover its calls, storage and
+/// events without reading its source. This is generated by `#[macros::runtime]` as
+/// `Runtime::metadata()`.
+#[derive(Debug)]
+pub struct RuntimeMetadata {
+    pub pallets: Vec<PalletMetadata>,
+}
+
+#[derive(Debug)]
+pub struct PalletMetadata {
+    /// The name of the pallet in the runtime.
+    pub name: &'static str,
+    /// The index of the pallet in the runtime, from `#[pallet_index(n)]`. System has the index 0.
+    pub index: u8,
+    pub calls: Vec<CallMetadata>,
+    pub storage: Vec<StorageEntryMetadata>,
+    pub constants: Vec<ConstantMetadata>,
+    pub events: Vec<EventMetadata>,
+}
+
+/// A callable function of a pallet, generated by `#[macros::call]`.
+#[derive(Debug)]
+pub struct CallMetadata {
+    pub name: &'static str,
+    /// The index of the call in its pallet, from `#[call_index(n)]`.
+    pub index: u8,
+    /// The arguments of the call, not including the caller or origin.
+    pub args: Vec<ArgMetadata>,
+    pub docs: Vec<&'static str>,
+    /// The note of the `#[deprecated]` attribute of the call, or an empty note when it has none.
+    /// This is `None` when the call is not deprecated.
+    pub deprecated: Option<&'static str>,
+}
+
+#[derive(Debug)]
+pub struct ArgMetadata {
+    pub name: &'static str,
+    /// The type of the argument in the runtime, like `u128` for `T::Balance`.
+    pub ty: &'static str,
+}
+
+/// A storage item of a pallet, generated by `#[macros::storage]`.
+#[derive(Debug)]
+pub struct StorageEntryMetadata {
+    pub name: &'static str,
+    /// The kind of the item, `StorageValue`, `StorageMap` or `StorageDoubleMap`.
+    pub kind: &'static str,
+    /// The types of the keys of the item in the runtime, one for a map and two for a double map.
+    pub keys: Vec<&'static str>,
+    /// The type of the values of the item in the runtime, like `u128` for `T::Balance`.
+    pub value: &'static str,
+    pub docs: Vec<&'static str>,
+}
+
+impl StorageEntryMetadata {
+    pub fn new<S: StorageEntryType>(name: &'static str, docs: Vec<&'static str>) -> Self {
+        Self { name, kind: S::KIND, keys: S::keys(), value: S::value(), docs }
+    }
+}
+
+/// The kind, key types and value type of a storage item. The items in `storage` implement this.
+pub trait StorageEntryType {
+    const KIND: &'static str;
+    fn keys() -> Vec<&'static str>;
+    fn value() -> &'static str;
+}
+
+/// A constant of the `Config` of a pallet, with its value in the runtime.
+#[derive(Debug)]
+pub struct ConstantMetadata {
+    pub name: &'static str,
+    /// The type of the constant in the runtime, like `u128` for `T::Balance`.
+    pub ty: &'static str,
+    /// The value of the constant, formatted with `Debug`.
+    pub value: String,
+}
+
+impl ConstantMetadata {
+    pub fn new<V: core::fmt::Debug>(name: &'static str, value: V) -> Self {
+        Self { name, ty: core::any::type_name::<V>(), value: format!("{:?}", value) }
+    }
+}
+
+/// An event a pallet can deposit.
+#[derive(Debug)]
+pub struct EventMetadata {
+    pub name: &'static str,
+    /// The index of the variant, which its encoding starts with.
+    pub index: u8,
+    /// The fields of the event, with their types in the runtime.
+    pub fields: Vec<ArgMetadata>,
+    pub docs: Vec<&'static str>,
+}
+
+/// The storage items of a pallet. This is implemented by `#[macros::storage]`.
+pub trait PalletStorageMetadata {
+    fn storage_metadata() -> Vec<StorageEntryMetadata>;
+}
+
+/// The constants of a pallet. Pallets with constants in their `Config` implement this by hand.
+pub trait PalletConstantMetadata {
+    fn constant_metadata() -> Vec<ConstantMetadata>;
+}
+
+/// The events of a pallet. Pallets with an `Event` implement this by hand.
+pub trait PalletEventMetadata {
+    fn event_metadata() -> Vec<EventMetadata>;
+}
+
+/// Used by `#[macros::runtime]` to describe a pallet `P`, falling back to no storage items,
+/// constants or events when `P` does not implement `PalletStorageMetadata`,
+/// `PalletConstantMetadata` or `PalletEventMetadata`.
+///
+/// The runtime calls the methods below on `&MetadataOf<P>`. Method resolution first looks for a
+/// method taking that type, which the traits implemented for `MetadataOf<P>` provide when `P`
+/// implements the metadata trait, and only then for a method taking `&&MetadataOf<P>`, which the
+/// fallbacks implemented for `&MetadataOf<P>` provide.
+pub struct MetadataOf<P>(pub core::marker::PhantomData<P>);
+
+pub trait DescribeStorage {
+    fn storage_metadata(&self) -> Vec<StorageEntryMetadata>;
+}
+
+impl<P: PalletStorageMetadata> DescribeStorage for MetadataOf<P> {
+    fn storage_metadata(&self) -> Vec<StorageEntryMetadata> {
+        P::storage_metadata()
+    }
+}
+
+// Every pallet of the runtime in `main.rs` keeps its state in storage, so only other runtimes use
+// this fallback.
+#[allow(dead_code)]
+pub trait DescribeNoStorage {
+    fn storage_metadata(&self) -> Vec<StorageEntryMetadata> {
+        Vec::new()
+    }
+}
+
+impl<P> DescribeNoStorage for &MetadataOf<P> {}
+
+pub trait DescribeConstants {
+    fn constant_metadata(&self) -> Vec<ConstantMetadata>;
+}
+
+impl<P: PalletConstantMetadata> DescribeConstants for MetadataOf<P> {
+    fn constant_metadata(&self) -> Vec<ConstantMetadata> {
+        P::constant_metadata()
+    }
+}
+
+pub trait DescribeNoConstants {
+    fn constant_metadata(&self) -> Vec<ConstantMetadata> {
+        Vec::new()
+    }
+}
+
+impl<P> DescribeNoConstants for &MetadataOf<P> {}
+
+pub trait DescribeEvents {
+    fn event_metadata(&self) -> Vec<EventMetadata>;
+}
+
+impl<P: PalletEventMetadata> DescribeEvents for MetadataOf<P> {
+    fn event_metadata(&self) -> Vec<EventMetadata> {
+        P::event_metadata()
+    }
+}
+
+pub trait DescribeNoEvents {
+    fn event_metadata(&self) -> Vec<EventMetadata> {
+        Vec::new()
+    }
+}
+
+impl<P> DescribeNoEvents for &MetadataOf<P> {}
+
+// A JSON string, with the characters JSON does not allow escaped.
+fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// A JSON array of already serialized items.
+fn json_array(items: impl IntoIterator<Item = String>) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(","))
+}
+
+fn json_docs(docs: &[&str]) -> String {
+    json_array(docs.iter().map(|doc| json_string(doc)))
+}
+
+impl RuntimeMetadata {
+    /// The pallet named `name` in the runtime.
+    pub fn pallet(&self, name: &str) -> Option<&PalletMetadata> {
+        self.pallets.iter().find(|pallet| pallet.name == name)
+    }
+
+    /// Serialize the metadata to JSON.
+    pub fn to_json(&self) -> String {
+        format!("{{\"pallets\":{}}}", json_array(self.pallets.iter().map(PalletMetadata::to_json)))
+    }
+}
+
+impl PalletMetadata {
+    /// The call of this pallet named `name`.
+    pub fn call(&self, name: &str) -> Option<&CallMetadata> {
+        self.calls.iter().find(|call| call.name == name)
+    }
+
+    /// The storage item of this pallet named `name`.
+    pub fn storage_entry(&self, name: &str) -> Option<&StorageEntryMetadata> {
+        self.storage.iter().find(|entry| entry.name == name)
+    }
+
+    /// The constant of this pallet named `name`.
+    pub fn constant(&self, name: &str) -> Option<&ConstantMetadata> {
+        self.constants.iter().find(|constant| constant.name == name)
+    }
+
+    /// The event of this pallet named `name`.
+    pub fn event(&self, name: &str) -> Option<&EventMetadata> {
+        self.events.iter().find(|event| event.name == name)
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"name\":{},\"index\":{},\"calls\":{},\"storage\":{},\"constants\":{},\
+             \"events\":{}}}",
+            json_string(self.name),
+            self.index,
+            json_array(self.calls.iter().map(CallMetadata::to_json)),
+            json_array(self.storage.iter().map(StorageEntryMetadata::to_json)),
+            json_array(self.constants.iter().map(ConstantMetadata::to_json)),
+            json_array(self.events.iter().map(EventMetadata::to_json))
+        )
+    }
+}
+
+impl CallMetadata {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"name\":{},\"index\":{},\"args\":{},\"docs\":{},\"deprecated\":{}}}",
+            json_string(self.name),
+            self.index,
+            json_array(self.args.iter().map(ArgMetadata::to_json)),
+            json_docs(&self.docs),
+            self.deprecated.map(json_string).unwrap_or_else(|| "null".to_string())
+        )
+    }
+}
+
+impl ArgMetadata {
+    fn to_json(&self) -> String {
+        format!("{{\"name\":{},\"ty\":{}}}", json_string(self.name), json_string(self.ty))
+    }
+}
+
+impl StorageEntryMetadata {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"name\":{},\"kind\":{},\"keys\":{},\"value\":{},\"docs\":{}}}",
+            json_string(self.name),
+            json_string(self.kind),
+            json_array(self.keys.iter().map(|key| json_string(key))),
+            json_string(self.value),
+            json_docs(&self.docs)
+        )
+    }
+}
+
+impl EventMetadata {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"name\":{},\"index\":{},\"fields\":{},\"docs\":{}}}",
+            json_string(self.name),
+            self.index,
+            json_array(self.fields.iter().map(ArgMetadata::to_json)),
+            json_docs(&self.docs)
+        )
+    }
+}
+
+impl ConstantMetadata {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"name\":{},\"ty\":{},\"value\":{}}}",
+            json_string(self.name),
+            json_string(self.ty),
+            json_string(&self.value)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Runtime;
+
+    // A pallet with a deprecated call, and two calls sharing an index of which only one is compiled
+    // in. The one compiled out is the only call to use `T`.
+    mod example {
+        use crate::support::DispatchResult;
+
+        pub trait Config: crate::system::Config {}
+
+        pub struct Pallet<T: Config>(pub core::marker::PhantomData<T>);
+
+        #[macros::call]
+        impl<T: Config> Pallet<T> {
+            /// Do nothing.
+            #[call_index(0)]
+            #[deprecated(since = "0.2.0", note = "Use `remark` of system instead")]
+            pub fn noop(&mut self, _caller: T::AccountId) -> DispatchResult {
+                Ok(())
+            }
+
+            #[call_index(1)]
+            #[cfg(any())]
+            pub fn compiled_out(
+                &mut self,
+                _caller: T::AccountId,
+                value: T::BlockNumber
+            ) -> DispatchResult {
+                Ok(())
+            }
+
+            #[call_index(1)]
+            #[cfg(test)]
+            pub fn compiled_in(&mut self, _caller: T::AccountId) -> DispatchResult {
+                Ok(())
+            }
+        }
+    }
+
+    impl example::Config for Runtime {}
+
+    #[test]
+    fn runtime_metadata() {
+        let metadata = Runtime::metadata();
+        let names = metadata.pallets.iter().map(|pallet| pallet.name).collect::<Vec<_>>();
+        assert_eq!(&names[..3], ["system", "timestamp", "balances"]);
+
+        let balances = metadata.pallet("balances").unwrap();
+        assert_eq!(balances.index, 2);
+        let free = balances.storage_entry("balances").unwrap();
+        assert_eq!(free.kind, "StorageMap");
+        assert_eq!((free.keys.as_slice(), free.value), (&["alloc::string::String"][..], "u128"));
+        assert_eq!(free.docs, ["The free funds of each account."]);
+        let force_set_balance = balances.call("force_set_balance").unwrap();
+        assert_eq!(force_set_balance.index, 1);
+        assert_eq!(force_set_balance.args[1].name, "amount");
+        assert_eq!(force_set_balance.args[1].ty, "u128");
+        assert_eq!(
+            force_set_balance.docs,
+            ["Set the balance of `who` to `amount`. Only root can make this call."]
+        );
+
+        let json = metadata.to_json();
+        assert!(json.starts_with("{\"pallets\":[{\"name\":\"system\",\"index\":0,\"calls\":[{"));
+        assert_eq!(metadata.pallet("system").unwrap().call("remark").unwrap().index, 0);
+        assert!(json.contains("{\"name\":\"amount\",\"ty\":\"u128\"}"));
+    }
+
+    #[test]
+    fn constants_are_described() {
+        let metadata = Runtime::metadata();
+        let system = metadata.pallet("system").unwrap();
+        let max_block_weight = system.constant("MAX_BLOCK_WEIGHT").unwrap();
+        assert_eq!((max_block_weight.ty, max_block_weight.value.as_str()), ("u64", "1000"));
+        let timestamp = metadata.pallet("timestamp").unwrap();
+        assert_eq!(timestamp.constant("MINIMUM_PERIOD").unwrap().value, "3000");
+        let treasury = metadata.pallet("treasury").unwrap();
+        assert_eq!(treasury.constant("PALLET_ID").unwrap().value, "\"py/trsry\"");
+        let staking = metadata.pallet("staking").unwrap();
+        assert_eq!(staking.constant("VALIDATOR_COUNT").unwrap().ty, "usize");
+        let identity = metadata.pallet("identity").unwrap();
+        assert_eq!(identity.constant("BASIC_DEPOSIT").unwrap().value, "10");
+
+        // Pallets without constants are described with none.
+        assert!(metadata.pallet("proxy").unwrap().constants.is_empty());
+        assert!(metadata.to_json().contains(
+            "\"constants\":[{\"name\":\"MINIMUM_PERIOD\",\"ty\":\"u64\",\"value\":\"3000\"}"
+        ));
+    }
+
+    #[test]
+    fn storage_and_events_are_described_with_runtime_types() {
+        let metadata = Runtime::metadata();
+        let system = metadata.pallet("system").unwrap();
+        let block_number = system.storage_entry("block_number").unwrap();
+        assert_eq!((block_number.kind, block_number.value), ("StorageValue", "u32"));
+        assert!(block_number.keys.is_empty());
+        let assets = metadata.pallet("assets").unwrap();
+        let accounts = assets.storage_entry("accounts").unwrap();
+        assert_eq!(accounts.kind, "StorageDoubleMap");
+        assert_eq!(accounts.keys, ["u32", "alloc::string::String"]);
+        assert_eq!(accounts.value, "u128");
+
+        let remarked = system.event("Remarked").unwrap();
+        assert_eq!(remarked.index, 0);
+        let fields = remarked.fields.iter().map(|field| (field.name, field.ty)).collect::<Vec<_>>();
+        assert_eq!(fields, [("sender", "alloc::string::String"), ("hash", "[u8; 8]")]);
+        assert!(metadata.pallet("balances").unwrap().events.is_empty());
+        assert!(metadata.to_json().contains(
+            "\"events\":[{\"name\":\"Remarked\",\"index\":0,\"fields\":[{\"name\":\"sender\""
+        ));
+    }
+
+    #[test]
+    fn call_attributes_are_kept() {
+        let calls = example::Call::<Runtime>::metadata();
+        let call = |name| calls.iter().find(|call| call.name == name);
+        assert_eq!(calls.len(), 2);
+        assert!(call("compiled_out").is_none());
+        assert_eq!(call("compiled_in").unwrap().index, 1);
+        let noop = call("noop").unwrap();
+        assert_eq!(noop.docs, ["Do nothing."]);
+        assert_eq!(noop.deprecated, Some("Use `remark` of system instead"));
+        assert!(noop.to_json().ends_with("\"deprecated\":\"Use `remark` of system instead\"}"));
+
+        let metadata = Runtime::metadata();
+        let transfer = metadata.pallet("balances").unwrap().call("transfer").unwrap();
+        assert_eq!(transfer.deprecated, None);
+
+        // Deprecated calls can still be built and called.
+        #[allow(deprecated)]
+        let call = example::Call::<Runtime>::new_noop();
+        assert_eq!(call.name(), "noop");
+        let mut pallet = example::Pallet::<Runtime>(core::marker::PhantomData);
+        #[allow(deprecated)]
+        let result = pallet.noop("alice".to_string());
+        assert_eq!(result, Ok(()));
+        assert_eq!(pallet.compiled_in("alice".to_string()), Ok(()));
+    }
+
+    #[test]
+    fn calls_are_encoded_with_their_indices() {
+        use crate::{ balances, storage::Encode, RuntimeCall };
+
+        let call = RuntimeCall::balances(balances::Call::force_set_balance {
+            who: "alice".to_string(),
+            amount: 100,
+        });
+        let encoded = call.encode();
+        let metadata = Runtime::metadata();
+        let balances = metadata.pallet("balances").unwrap();
+        let force_set_balance = balances.call("force_set_balance").unwrap();
+        assert_eq!(encoded[..2], [balances.index, force_set_balance.index]);
+        assert_eq!(encoded[2..], ("alice".to_string(), 100u128).encode());
+    }
+
+    #[test]
+    fn calls_can_be_built_compared_and_named() {
+        use crate::{ balances, support::{ GetCallName, Instance2 }, RuntimeCall };
+
+        let transfer = |amount| balances::Call::transfer { to: "bob".to_string(), amount };
+        assert_eq!(transfer(10), transfer(10).clone());
+        assert_ne!(transfer(10), transfer(20));
+        assert_eq!(transfer(10).name(), "transfer");
+        assert_eq!(transfer(10).pallet_name(), "balances");
+
+        // The call of another instance is named after its field in the runtime.
+        let credits: RuntimeCall =
+            balances::Call::<_, Instance2>::new_transfer("bob".to_string(), 10).into();
+        assert!(matches!(&credits, RuntimeCall::credits(balances::Call::transfer { .. })));
+        assert_ne!(RuntimeCall::balances(transfer(10)), credits);
+        assert_eq!(RuntimeCall::balances(transfer(10)), RuntimeCall::balances(transfer(10)));
+        assert_eq!((credits.pallet_name(), credits.call_name()), ("credits", "transfer"));
+        assert_eq!(
+            format!("{:?}", credits),
+            "credits(transfer { to: \"bob\", amount: 10 })"
+        );
+    }
+}
@@ -0,0 +1,432 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+use num::traits::{ CheckedAdd, One, Zero };
+use crate::balances;
+use crate::metadata::ConstantMetadata;
+use crate::storage::{ Decode, Encode, StorageDoubleMap, StorageMap, StorageValue };
+use crate::support::DispatchResult;
+
+/// The key of an attribute of an item.
+pub type AttributeKey = String;
+/// The value of an attribute of an item.
+pub type AttributeValue = String;
+
+pub trait Config: Sized +
+    crate::balances::Config +
+    AsMut<crate::balances::Pallet<Self>> +
+    AsMut<Pallet<Self>> {
+    /// The identifier of a collection. Collections are numbered in the order they are created.
+    type CollectionId: Zero + One + CheckedAdd + Ord + Copy + Debug + Encode + Decode;
+    /// The identifier of an item within its collection, chosen when it is minted.
+    type ItemId: Ord + Copy + Debug + Encode + Decode;
+
+    /// The deposit reserved from the owner of a collection, until the collection is destroyed.
+    const COLLECTION_DEPOSIT: Self::Balance;
+}
+
+/// A collection of items, which only its owner can mint.
+#[derive(Debug)]
+pub struct CollectionDetails<T: Config> {
+    pub owner: T::AccountId,
+    pub deposit: T::Balance,
+    /// The number of items in the collection.
+    pub items: u32,
+    /// Frozen collections cannot be minted, transferred or changed.
+    pub frozen: bool,
+}
+
+impl<T: Config> Encode for CollectionDetails<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.owner.encode_to(dest);
+        self.deposit.encode_to(dest);
+        self.items.encode_to(dest);
+        self.frozen.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for CollectionDetails<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            owner: Decode::decode(input)?,
+            deposit: Decode::decode(input)?,
+            items: Decode::decode(input)?,
+            frozen: Decode::decode(input)?,
+        })
+    }
+}
+
+/// A unique item of a collection.
+#[derive(Debug)]
+pub struct ItemDetails<T: Config> {
+    pub owner: T::AccountId,
+    /// An account allowed to transfer the item on behalf of its owner.
+    pub approved: Option<T::AccountId>,
+    pub attributes: BTreeMap<AttributeKey, AttributeValue>,
+}
+
+impl<T: Config> Encode for ItemDetails<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.owner.encode_to(dest);
+        self.approved.encode_to(dest);
+        self.attributes.iter().collect::<Vec<_>>().encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for ItemDetails<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let owner = Decode::decode(input)?;
+        let approved = Decode::decode(input)?;
+        let attributes = Vec::<(AttributeKey, AttributeValue)>::decode(input)?;
+        Some(Self { owner, approved, attributes: attributes.into_iter().collect() })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The details of each collection.
+    collections: StorageMap<T::CollectionId, CollectionDetails<T>>,
+    /// The id of the next collection to be created.
+    next_collection_id: StorageValue<T::CollectionId>,
+    /// The items of each collection.
+    items: StorageDoubleMap<T::CollectionId, T::ItemId, ItemDetails<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn collection(&self, collection: &T::CollectionId) -> Option<CollectionDetails<T>> {
+        self.collections.get(collection)
+    }
+
+    pub fn item(&self, collection: &T::CollectionId, item: &T::ItemId) -> Option<ItemDetails<T>> {
+        self.items.get(collection, item)
+    }
+
+    /// The owner of an item.
+    pub fn owner(&self, collection: &T::CollectionId, item: &T::ItemId) -> Option<T::AccountId> {
+        self.item(collection, item).map(|details| details.owner)
+    }
+
+    pub fn attribute(
+        &self,
+        collection: &T::CollectionId,
+        item: &T::ItemId,
+        key: &AttributeKey
+    ) -> Option<AttributeValue> {
+        self.item(collection, item).and_then(|mut details| details.attributes.remove(key))
+    }
+
+    // Check that `caller` owns `collection`, and that it is not frozen.
+    fn ensure_collection_owner(
+        &self,
+        caller: &T::AccountId,
+        collection: &T::CollectionId
+    ) -> DispatchResult {
+        let details = self.collection(collection).ok_or("Collection does not exist")?;
+        if details.owner != *caller {
+            return Err("You are not the owner of this collection");
+        }
+        if details.frozen {
+            return Err("Collection is frozen");
+        }
+        Ok(())
+    }
+
+    // Get an item of a collection which is not frozen, to change it.
+    fn thawed_item(
+        &self,
+        collection: &T::CollectionId,
+        item: &T::ItemId
+    ) -> Result<ItemDetails<T>, &'static str> {
+        let details = self.collection(collection).ok_or("Collection does not exist")?;
+        if details.frozen {
+            return Err("Collection is frozen");
+        }
+        self.item(collection, item).ok_or("Item does not exist")
+    }
+
+    // Freeze or thaw a collection.
+    fn set_frozen(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId,
+        frozen: bool
+    ) -> DispatchResult {
+        let mut details = self.collection(&collection).ok_or("Collection does not exist")?;
+        if details.owner != caller {
+            return Err("You are not the owner of this collection");
+        }
+        details.frozen = frozen;
+        self.collections.insert(&collection, &details);
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("COLLECTION_DEPOSIT", T::COLLECTION_DEPOSIT)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Create a new collection owned by the caller, reserving `COLLECTION_DEPOSIT`.
+    #[call_index(0)]
+    pub fn create_collection(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+        let nfts: &mut Pallet<T> = runtime.as_mut();
+        let collection = nfts.next_collection_id.get().unwrap_or(T::CollectionId::zero());
+        let next_collection_id = collection.checked_add(&T::CollectionId::one()).ok_or("Overflow")?;
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.reserve(&caller, T::COLLECTION_DEPOSIT)?;
+
+        let nfts: &mut Pallet<T> = runtime.as_mut();
+        nfts.next_collection_id.put(&next_collection_id);
+        let details = CollectionDetails {
+            owner: caller,
+            deposit: T::COLLECTION_DEPOSIT,
+            items: 0,
+            frozen: false,
+        };
+        nfts.collections.insert(&collection, &details);
+        Ok(())
+    }
+
+    /// Destroy an empty collection of the caller, returning its deposit.
+    #[call_index(1)]
+    pub fn destroy_collection(
+        runtime: &mut T,
+        caller: T::AccountId,
+        collection: T::CollectionId
+    ) -> DispatchResult {
+        let nfts: &mut Pallet<T> = runtime.as_mut();
+        let details = nfts.collection(&collection).ok_or("Collection does not exist")?;
+        if details.owner != caller {
+            return Err("You are not the owner of this collection");
+        }
+        if details.items > 0 {
+            return Err("Collection still has items");
+        }
+        let deposit = details.deposit;
+        nfts.collections.remove(&collection);
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.unreserve(&caller, deposit);
+        Ok(())
+    }
+
+    /// Mint `item` in `collection`, owned by `owner`. Only the owner of the collection can mint.
+    #[call_index(2)]
+    pub fn mint(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId,
+        item: T::ItemId,
+        owner: T::AccountId
+    ) -> DispatchResult {
+        self.ensure_collection_owner(&caller, &collection)?;
+        if self.item(&collection, &item).is_some() {
+            return Err("Item already exists");
+        }
+        let mut details = self.collection(&collection).ok_or("Collection does not exist")?;
+        details.items = details.items.checked_add(1).ok_or("Overflow")?;
+        self.collections.insert(&collection, &details);
+
+        let details = ItemDetails { owner, approved: None, attributes: BTreeMap::new() };
+        self.items.insert(&collection, &item, &details);
+        Ok(())
+    }
+
+    /// Transfer an item to `dest`. The caller must own the item, or be approved to transfer it.
+    #[call_index(3)]
+    pub fn transfer(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId,
+        item: T::ItemId,
+        dest: T::AccountId
+    ) -> DispatchResult {
+        let mut details = self.thawed_item(&collection, &item)?;
+        if details.owner != caller && details.approved.as_ref() != Some(&caller) {
+            return Err("You are not allowed to transfer this item");
+        }
+        details.owner = dest;
+        details.approved = None;
+        self.items.insert(&collection, &item, &details);
+        Ok(())
+    }
+
+    /// Destroy an item of the caller, with all of its attributes.
+    #[call_index(4)]
+    pub fn burn(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId,
+        item: T::ItemId
+    ) -> DispatchResult {
+        let details = self.item(&collection, &item).ok_or("Item does not exist")?;
+        if details.owner != caller {
+            return Err("You are not the owner of this item");
+        }
+        self.items.remove(&collection, &item);
+        if let Some(mut details) = self.collection(&collection) {
+            details.items -= 1;
+            self.collections.insert(&collection, &details);
+        }
+        Ok(())
+    }
+
+    /// Allow `delegate` to transfer an item of the caller once, replacing any previous approval.
+    #[call_index(5)]
+    pub fn approve_transfer(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId,
+        item: T::ItemId,
+        delegate: T::AccountId
+    ) -> DispatchResult {
+        let mut details = self.thawed_item(&collection, &item)?;
+        if details.owner != caller {
+            return Err("You are not the owner of this item");
+        }
+        details.approved = Some(delegate);
+        self.items.insert(&collection, &item, &details);
+        Ok(())
+    }
+
+    /// Set the attribute `key` of an item. Only the owner of the collection can set attributes.
+    #[call_index(6)]
+    pub fn set_attribute(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId,
+        item: T::ItemId,
+        key: AttributeKey,
+        value: AttributeValue
+    ) -> DispatchResult {
+        self.ensure_collection_owner(&caller, &collection)?;
+        let mut details = self.thawed_item(&collection, &item)?;
+        details.attributes.insert(key, value);
+        self.items.insert(&collection, &item, &details);
+        Ok(())
+    }
+
+    #[call_index(7)]
+    pub fn clear_attribute(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId,
+        item: T::ItemId,
+        key: AttributeKey
+    ) -> DispatchResult {
+        self.ensure_collection_owner(&caller, &collection)?;
+        let mut details = self.thawed_item(&collection, &item)?;
+        details.attributes.remove(&key).ok_or("Attribute does not exist")?;
+        self.items.insert(&collection, &item, &details);
+        Ok(())
+    }
+
+    /// Freeze a collection of the caller, so its items cannot be minted, transferred or changed.
+    #[call_index(8)]
+    pub fn freeze_collection(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId
+    ) -> DispatchResult {
+        self.set_frozen(caller, collection, true)
+    }
+
+    #[call_index(9)]
+    pub fn thaw_collection(
+        &mut self,
+        caller: T::AccountId,
+        collection: T::CollectionId
+    ) -> DispatchResult {
+        self.set_frozen(caller, collection, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pallet;
+    use crate::{ support::{ Dispatch, Origin }, Runtime, RuntimeCall };
+
+    #[test]
+    fn mint_transfer_and_burn() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let charlie = "charlie".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(Pallet::create_collection(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+        assert_eq!(
+            runtime.nfts.mint(bob.clone(), 0, 1, bob.clone()),
+            Err("You are not the owner of this collection")
+        );
+        assert_eq!(runtime.nfts.mint(alice.clone(), 0, 1, bob.clone()), Ok(()));
+        assert_eq!(runtime.nfts.mint(alice.clone(), 0, 1, bob.clone()), Err("Item already exists"));
+
+        // Bob approves charlie, who can then transfer the item once.
+        assert_eq!(
+            runtime.nfts.transfer(charlie.clone(), 0, 1, charlie.clone()),
+            Err("You are not allowed to transfer this item")
+        );
+        assert_eq!(runtime.nfts.approve_transfer(bob.clone(), 0, 1, charlie.clone()), Ok(()));
+        assert_eq!(runtime.nfts.transfer(charlie.clone(), 0, 1, alice.clone()), Ok(()));
+        assert_eq!(runtime.nfts.owner(&0, &1), Some(alice.clone()));
+        assert_eq!(runtime.nfts.item(&0, &1).unwrap().approved, None);
+
+        assert_eq!(
+            Pallet::destroy_collection(&mut runtime, alice.clone(), 0),
+            Err("Collection still has items")
+        );
+        assert_eq!(runtime.nfts.burn(alice.clone(), 0, 1), Ok(()));
+        assert_eq!(Pallet::destroy_collection(&mut runtime, alice.clone(), 0), Ok(()));
+        assert_eq!(runtime.balances.balance(&alice), 100);
+    }
+
+    #[test]
+    fn attributes_and_freezing() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let color = "color".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        assert_eq!(Pallet::create_collection(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.nfts.mint(alice.clone(), 0, 7, bob.clone()), Ok(()));
+
+        assert_eq!(
+            runtime.nfts.set_attribute(alice.clone(), 0, 7, color.clone(), "red".to_string()),
+            Ok(())
+        );
+        assert_eq!(runtime.nfts.attribute(&0, &7, &color), Some("red".to_string()));
+
+        // Nothing in a frozen collection can change, until it is thawed.
+        assert_eq!(runtime.nfts.freeze_collection(alice.clone(), 0), Ok(()));
+        let transfer = RuntimeCall::nfts(super::Call::transfer {
+            collection: 0,
+            item: 7,
+            dest: alice.clone(),
+        });
+        assert_eq!(
+            runtime.dispatch(Origin::Signed(bob.clone()), transfer.clone()),
+            Err("Collection is frozen")
+        );
+        assert_eq!(
+            runtime.nfts.clear_attribute(alice.clone(), 0, 7, color.clone()),
+            Err("Collection is frozen")
+        );
+        assert_eq!(runtime.nfts.thaw_collection(alice.clone(), 0), Ok(()));
+        assert_eq!(runtime.nfts.clear_attribute(alice.clone(), 0, 7, color.clone()), Ok(()));
+        assert_eq!(runtime.nfts.attribute(&0, &7, &color), None);
+        assert_eq!(runtime.dispatch(Origin::Signed(bob), transfer), Ok(()));
+        assert_eq!(runtime.nfts.owner(&0, &7), Some(alice.clone()));
+    }
+}
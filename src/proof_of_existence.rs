@@ -1,49 +1,49 @@
 use core::fmt::Debug;
-use std::collections::BTreeMap;
+use crate::storage::{ Decode, Encode, StorageMap };
 
 pub trait Config: crate::system::Config {
-    type Content: Debug + Ord;
+    type Content: Debug + Ord + Encode + Decode;
 }
 
+#[macros::storage]
 #[derive(Debug)]
 pub struct Pallet<T: Config> {
-    claims: BTreeMap<T::Content, T::AccountId>,
+    /// The owner of each claimed content.
+    claims: StorageMap<T::Content, T::AccountId>,
 }
 
 impl<T: Config> Pallet<T> {
-    pub fn new() -> Self {
-        Self {
-            claims: BTreeMap::new(),
-        }
-    }
-
-    pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
+    pub fn get_claim(&self, claim: &T::Content) -> Option<T::AccountId> {
         self.claims.get(claim)
     }
 }
 
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
 #[macros::call]
 impl<T: Config> Pallet<T> {
+    #[call_index(0)]
     pub fn create_claim(
         &mut self,
         caller: T::AccountId,
         claim: T::Content
     ) -> crate::support::DispatchResult {
-        if self.claims.contains_key(&claim) {
-            return Err(&"This content is already claimed");
+        if self.claims.get(&claim).is_some() {
+            return Err("This content is already claimed");
         }
-        self.claims.insert(claim, caller);
+        self.claims.insert(&claim, &caller);
         Ok(())
     }
 
+    #[call_index(1)]
     pub fn revoke_claim(
         &mut self,
         caller: T::AccountId,
         claim: T::Content
     ) -> crate::support::DispatchResult {
         let owner = self.get_claim(&claim).ok_or("Claim does not exist")?;
-        if caller != *owner {
-            return Err(&"You are not the owner of this claim");
+        if caller != owner {
+            return Err("You are not the owner of this claim");
         }
         self.claims.remove(&claim);
         Ok(())
@@ -53,6 +53,7 @@ impl<T: Config> Pallet<T> {
 #[cfg(test)]
 mod test {
     struct TestConfig;
+    use crate::storage::Store;
     use crate::types::{ AccountId, BlockNumber, Nonce, Content };
 
     impl super::Config for TestConfig {
@@ -63,23 +64,27 @@ mod test {
         type AccountId = AccountId;
         type BlockNumber = BlockNumber;
         type Nonce = Nonce;
+        type BaseCallFilter = ();
+
+        const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+        const EXTRINSIC_WEIGHT: crate::support::Weight = 10;
     }
 
     #[test]
     fn basic_proof_of_existence() {
         let alice = "alice".to_string();
         let bob = "bob".to_string();
-        let alice_claim = "alice_claim";
-        let bob_claim = "bob_claim";
+        let alice_claim = "alice_claim".to_string();
+        let bob_claim = "bob_claim".to_string();
 
-        let mut poe = super::Pallet::<TestConfig>::new();
+        let mut poe = super::Pallet::<TestConfig>::new(&Store::new());
 
         assert_eq!(poe.get_claim(&alice_claim), None);
-        assert_eq!(poe.create_claim(alice.clone(), &alice_claim), Ok(()));
-        assert_eq!(poe.get_claim(&alice_claim), Some(&alice));
+        assert_eq!(poe.create_claim(alice.clone(), alice_claim.clone()), Ok(()));
+        assert_eq!(poe.get_claim(&alice_claim), Some(alice));
 
-        assert_eq!(poe.create_claim(bob.clone(), &bob_claim), Ok(()));
-        assert_eq!(poe.revoke_claim(bob.clone(), bob_claim), Ok(()));
-        assert_eq!(poe.create_claim(bob, &bob_claim), Ok(()));
+        assert_eq!(poe.create_claim(bob.clone(), bob_claim.clone()), Ok(()));
+        assert_eq!(poe.revoke_claim(bob.clone(), bob_claim.clone()), Ok(()));
+        assert_eq!(poe.create_claim(bob, bob_claim), Ok(()));
     }
 }
@@ -0,0 +1,349 @@
+use core::fmt::Debug;
+use num::traits::{ CheckedAdd, Zero };
+use crate::storage::{ hash, Decode, Encode, StorageMap };
+use crate::support::{ Dispatch, DispatchResult, InstanceFilter, Origin };
+
+pub trait Config: Sized +
+    crate::system::Config +
+    AsMut<crate::system::Pallet<Self>> +
+    AsMut<Pallet<Self>> +
+    Dispatch<
+        Caller = Origin<<Self as crate::system::Config>::AccountId>,
+        Call = <Self as Config>::RuntimeCall
+    > {
+    /// The overarching call type, which a proxy dispatches on behalf of the real account. Calls are
+    /// announced by the hash of their encoding.
+    type RuntimeCall: Encode;
+    /// The kinds of proxies, each of which only allows a subset of `RuntimeCall`s.
+    type ProxyType: InstanceFilter<<Self as Config>::RuntimeCall> +
+        Clone + PartialEq + Debug + Encode + Decode;
+}
+
+/// An account authorized to dispatch some calls on behalf of a real account.
+#[derive(Debug)]
+pub struct ProxyDefinition<T: Config> {
+    pub delegate: T::AccountId,
+    pub proxy_type: T::ProxyType,
+    /// The number of blocks the delegate must wait after announcing, before it can act.
+    pub delay: T::BlockNumber,
+}
+
+impl<T: Config> Encode for ProxyDefinition<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.delegate.encode_to(dest);
+        self.proxy_type.encode_to(dest);
+        self.delay.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for ProxyDefinition<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let delegate = Decode::decode(input)?;
+        let proxy_type = Decode::decode(input)?;
+        Some(Self { delegate, proxy_type, delay: Decode::decode(input)? })
+    }
+}
+
+/// The hash of an encoded call, which a delegate announces before dispatching the call.
+pub type CallHash = [u8; 8];
+
+/// A delegate's announcement that it will dispatch the call with `call_hash` on behalf of `real`,
+/// made at block `height`.
+#[derive(Debug)]
+pub struct Announcement<T: Config> {
+    pub real: T::AccountId,
+    pub call_hash: CallHash,
+    pub height: T::BlockNumber,
+}
+
+impl<T: Config> Encode for Announcement<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.real.encode_to(dest);
+        self.call_hash.encode_to(dest);
+        self.height.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Announcement<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let real = Decode::decode(input)?;
+        let call_hash = Decode::decode(input)?;
+        Some(Self { real, call_hash, height: Decode::decode(input)? })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The proxies authorized by each real account.
+    proxies: StorageMap<T::AccountId, Vec<ProxyDefinition<T>>>,
+    /// The announcements made by each delegate.
+    announcements: StorageMap<T::AccountId, Vec<Announcement<T>>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// The proxies authorized by the `real` account.
+    pub fn proxies(&self, real: &T::AccountId) -> Vec<ProxyDefinition<T>> {
+        self.proxies.get(real).unwrap_or_default()
+    }
+
+    /// The announcements made by `delegate` which have not been used or rejected yet.
+    pub fn announcements(&self, delegate: &T::AccountId) -> Vec<Announcement<T>> {
+        self.announcements.get(delegate).unwrap_or_default()
+    }
+
+    // Store the announcements of `delegate`, removing the entry once none are left.
+    fn set_announcements(&mut self, delegate: &T::AccountId, announcements: Vec<Announcement<T>>) {
+        if announcements.is_empty() {
+            self.announcements.remove(delegate);
+        } else {
+            self.announcements.insert(delegate, &announcements);
+        }
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Authorize `delegate` to dispatch the calls allowed by `proxy_type` on behalf of the caller.
+    /// When `delay` is not zero, the delegate must `announce` each use and wait `delay` blocks.
+    #[call_index(0)]
+    pub fn add_proxy(
+        &mut self,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: T::ProxyType,
+        delay: T::BlockNumber
+    ) -> DispatchResult {
+        let mut proxies = self.proxies(&caller);
+        if proxies.iter().any(|p| p.delegate == delegate && p.proxy_type == proxy_type) {
+            return Err("This proxy already exists");
+        }
+        proxies.push(ProxyDefinition { delegate, proxy_type, delay });
+        self.proxies.insert(&caller, &proxies);
+        Ok(())
+    }
+
+    #[call_index(1)]
+    pub fn remove_proxy(
+        &mut self,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: T::ProxyType
+    ) -> DispatchResult {
+        let mut proxies = self.proxies(&caller);
+        let index = proxies
+            .iter()
+            .position(|p| p.delegate == delegate && p.proxy_type == proxy_type)
+            .ok_or("Proxy does not exist")?;
+        proxies.remove(index);
+        if proxies.is_empty() {
+            self.proxies.remove(&caller);
+        } else {
+            self.proxies.insert(&caller, &proxies);
+        }
+        Ok(())
+    }
+
+    /// Announce that the caller will dispatch the call with `call_hash` on behalf of `real`,
+    /// starting the delay of its proxy.
+    #[call_index(2)]
+    pub fn announce(
+        runtime: &mut T,
+        caller: T::AccountId,
+        real: T::AccountId,
+        call_hash: CallHash
+    ) -> DispatchResult {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        let height = system.block_number();
+
+        let proxy: &mut Pallet<T> = runtime.as_mut();
+        if !proxy.proxies(&real).iter().any(|p| p.delegate == caller) {
+            return Err("You are not a proxy of this account");
+        }
+        let mut announcements = proxy.announcements(&caller);
+        announcements.push(Announcement { real, call_hash, height });
+        proxy.set_announcements(&caller, announcements);
+        Ok(())
+    }
+
+    /// Reject all announcements made by `delegate` to act on behalf of the caller.
+    #[call_index(3)]
+    pub fn reject_announcement(
+        &mut self,
+        caller: T::AccountId,
+        delegate: T::AccountId
+    ) -> DispatchResult {
+        let mut announcements = self.announcements(&delegate);
+        let count = announcements.len();
+        announcements.retain(|a| a.real != caller);
+        if announcements.len() == count {
+            return Err("Announcement does not exist");
+        }
+        self.set_announcements(&delegate, announcements);
+        Ok(())
+    }
+
+    /// Dispatch `call` on behalf of `real`, as long as the caller is a proxy of `real` whose type
+    /// allows the call. A proxy with a delay must have announced the hash of the call long enough
+    /// ago, and uses up the announcement when the call succeeds.
+    #[call_index(4)]
+    pub fn proxy(
+        runtime: &mut T,
+        caller: T::AccountId,
+        real: T::AccountId,
+        call: Box<<T as Config>::RuntimeCall>
+    ) -> DispatchResult {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        let now = system.block_number();
+
+        let proxy: &mut Pallet<T> = runtime.as_mut();
+        let delay = proxy
+            .proxies(&real)
+            .iter()
+            .find(|p| p.delegate == caller && p.proxy_type.filter(&call))
+            .map(|p| p.delay)
+            .ok_or("You are not allowed to make this call on behalf of this account")?;
+
+        if delay.is_zero() {
+            return runtime.dispatch(Origin::Signed(real), *call);
+        }
+
+        let call_hash = hash(&call.encode());
+        let is_due = |a: &Announcement<T>| {
+            a.real == real &&
+                a.call_hash == call_hash &&
+                a.height.checked_add(&delay).is_some_and(|due| due <= now)
+        };
+        let announcements = proxy.announcements(&caller);
+        if !announcements.iter().any(|a| a.real == real && a.call_hash == call_hash) {
+            return Err("Announcement does not exist");
+        }
+        if !announcements.iter().any(is_due) {
+            return Err("Announcement is not yet due");
+        }
+
+        // The announcement is only used up once the call succeeds, so a failed call can be
+        // retried.
+        runtime.dispatch(Origin::Signed(real.clone()), *call)?;
+        let proxy: &mut Pallet<T> = runtime.as_mut();
+        let mut announcements = proxy.announcements(&caller);
+        if let Some(index) = announcements.iter().position(is_due) {
+            announcements.remove(index);
+        }
+        proxy.set_announcements(&caller, announcements);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pallet;
+    use crate::storage::{ hash, Encode };
+    use crate::{ balances, proof_of_existence, ProxyType, Runtime, RuntimeCall };
+
+    fn transfer(to: &str, amount: u128) -> Box<RuntimeCall> {
+        Box::new(
+            RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount })
+        )
+    }
+
+    #[test]
+    fn proxy_dispatches_allowed_calls() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        // Bob is not a proxy of alice yet.
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 10)),
+            Err("You are not allowed to make this call on behalf of this account")
+        );
+
+        assert_eq!(
+            runtime.proxy.add_proxy(alice.clone(), bob.clone(), ProxyType::Transfer, 0),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 10)),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 90);
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 10);
+
+        // A transfer proxy cannot make claims.
+        let claim = Box::new(
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                claim: "claim".to_string(),
+            })
+        );
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), claim),
+            Err("You are not allowed to make this call on behalf of this account")
+        );
+
+        assert_eq!(
+            runtime.proxy.remove_proxy(alice.clone(), bob.clone(), ProxyType::Transfer),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob, alice, transfer("charlie", 10)),
+            Err("You are not allowed to make this call on behalf of this account")
+        );
+    }
+
+    #[test]
+    fn delayed_proxy_needs_announcement() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let call_hash = |call: &RuntimeCall| hash(&call.encode());
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        assert_eq!(runtime.proxy.add_proxy(alice.clone(), bob.clone(), ProxyType::Any, 2), Ok(()));
+
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 10)),
+            Err("Announcement does not exist")
+        );
+
+        let announced = call_hash(&transfer("charlie", 10));
+        assert_eq!(Pallet::announce(&mut runtime, bob.clone(), alice.clone(), announced), Ok(()));
+        runtime.system.inc_block_number();
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 10)),
+            Err("Announcement is not yet due")
+        );
+
+        // Only the announced call can be dispatched.
+        runtime.system.inc_block_number();
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 20)),
+            Err("Announcement does not exist")
+        );
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 10)),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 90);
+        assert!(runtime.proxy.announcements(&bob).is_empty());
+
+        // A call which fails does not use up its announcement.
+        let announced = call_hash(&transfer("charlie", 1_000));
+        assert_eq!(Pallet::announce(&mut runtime, bob.clone(), alice.clone(), announced), Ok(()));
+        runtime.system.inc_block_number();
+        runtime.system.inc_block_number();
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 1_000)),
+            Err("Not enough funds.")
+        );
+        assert_eq!(runtime.proxy.announcements(&bob).len(), 1);
+
+        // Alice can reject the announcements of bob.
+        assert_eq!(runtime.proxy.reject_announcement(alice, bob.clone()), Ok(()));
+        assert!(runtime.proxy.announcements(&bob).is_empty());
+    }
+}
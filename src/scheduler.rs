@@ -0,0 +1,392 @@
+use core::fmt::Debug;
+use num::traits::{ CheckedAdd, One, Zero };
+use crate::storage::{ Decode, Encode, StorageMap };
+use crate::support::{ Dispatch, DispatchResult, Origin, Weight };
+
+pub trait Config: Sized +
+    crate::system::Config +
+    AsMut<crate::system::Pallet<Self>> +
+    AsMut<Pallet<Self>> +
+    Dispatch<
+        Caller = Origin<<Self as crate::system::Config>::AccountId>,
+        Call = <Self as Config>::RuntimeCall
+    > {
+    /// The overarching call type, which is dispatched when a scheduled task is due.
+    type RuntimeCall: Clone + Debug + Encode + Decode;
+}
+
+/// The name of a task, which can be used to cancel it.
+pub type TaskName = String;
+
+/// A call scheduled to be dispatched on behalf of `owner` at some future block.
+#[derive(Debug)]
+pub struct Scheduled<T: Config> {
+    pub name: Option<TaskName>,
+    pub owner: T::AccountId,
+    pub call: <T as Config>::RuntimeCall,
+    /// If set, the call is scheduled again every `period` blocks, `count` more times.
+    pub maybe_periodic: Option<(T::BlockNumber, u32)>,
+}
+
+impl<T: Config> Encode for Scheduled<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.name.encode_to(dest);
+        self.owner.encode_to(dest);
+        self.call.encode_to(dest);
+        self.maybe_periodic.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Scheduled<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            name: Decode::decode(input)?,
+            owner: Decode::decode(input)?,
+            call: Decode::decode(input)?,
+            maybe_periodic: Decode::decode(input)?,
+        })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The tasks scheduled for each block. Tasks are set to `None` when cancelled, so the index of
+    /// the other tasks does not change.
+    agenda: StorageMap<T::BlockNumber, Vec<Option<Scheduled<T>>>>,
+    /// The block and index of each named task.
+    lookup: StorageMap<TaskName, (T::BlockNumber, u32)>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// The tasks scheduled for block `when`, indexed as expected by `cancel`.
+    pub fn agenda(&self, when: &T::BlockNumber) -> Vec<Option<Scheduled<T>>> {
+        self.agenda.get(when).unwrap_or_default()
+    }
+
+    /// The block and index of the task named `name`.
+    pub fn lookup(&self, name: &TaskName) -> Option<(T::BlockNumber, u32)> {
+        self.lookup.get(name)
+    }
+
+    // Add a task to the agenda of block `when`, returning its index.
+    fn place_task(&mut self, when: T::BlockNumber, task: Scheduled<T>) -> u32 {
+        let mut agenda = self.agenda(&when);
+        let index = agenda.len() as u32;
+        if let Some(name) = &task.name {
+            self.lookup.insert(name, &(when, index));
+        }
+        agenda.push(Some(task));
+        self.agenda.insert(&when, &agenda);
+        index
+    }
+
+    // Check and add a new task, scheduled by one of the calls of this pallet.
+    fn do_schedule(runtime: &mut T, when: T::BlockNumber, task: Scheduled<T>) -> DispatchResult {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        if when <= system.block_number() {
+            return Err("Cannot schedule a call in the past");
+        }
+        if let Some((period, _)) = task.maybe_periodic {
+            if period.is_zero() {
+                return Err("The period of a task cannot be zero");
+            }
+        }
+
+        let scheduler: &mut Pallet<T> = runtime.as_mut();
+        if let Some(name) = &task.name {
+            if scheduler.lookup(name).is_some() {
+                return Err("A task with this name is already scheduled");
+            }
+        }
+        scheduler.place_task(when, task);
+        Ok(())
+    }
+
+    // Remove the task at `index` of the agenda of block `when`, as long as `caller` owns it.
+    fn do_cancel(
+        &mut self,
+        caller: &T::AccountId,
+        when: T::BlockNumber,
+        index: u32
+    ) -> DispatchResult {
+        let mut agenda = self.agenda(&when);
+        let task = agenda.get_mut(index as usize).ok_or("Task does not exist")?;
+        match task {
+            Some(scheduled) if scheduled.owner == *caller => {}
+            Some(_) => {
+                return Err("You are not the owner of this task");
+            }
+            None => {
+                return Err("Task does not exist");
+            }
+        }
+        if let Some(name) = task.take().and_then(|scheduled| scheduled.name) {
+            self.lookup.remove(&name);
+        }
+        self.agenda.insert(&when, &agenda);
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {
+    // Dispatch the tasks which are due in this block, and schedule the next run of periodic tasks.
+    // Each task uses the same weight as an extrinsic, and the tasks which do not fit in the weight
+    // left in the block are moved to the next block.
+    fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) -> Weight {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        let remaining_weight = system.remaining_weight();
+        let scheduler: &mut Pallet<T> = runtime.as_mut();
+        let agenda = scheduler.agenda(&block_number);
+        scheduler.agenda.remove(&block_number);
+        let mut tasks = agenda.into_iter().flatten();
+        let mut weight: Weight = 0;
+
+        while weight.saturating_add(T::EXTRINSIC_WEIGHT) <= remaining_weight {
+            let Some(Scheduled { name, owner, call, maybe_periodic }) = tasks.next() else {
+                break;
+            };
+            let scheduler: &mut Pallet<T> = runtime.as_mut();
+            if let Some(name) = &name {
+                scheduler.lookup.remove(name);
+            }
+
+            if let Some((period, count)) = maybe_periodic.filter(|(_, count)| *count > 0) {
+                if let Some(when) = block_number.checked_add(&period) {
+                    let task = Scheduled {
+                        name,
+                        owner: owner.clone(),
+                        call: call.clone(),
+                        maybe_periodic: Some((period, count - 1)),
+                    };
+                    scheduler.place_task(when, task);
+                }
+            }
+
+            let _res = runtime.dispatch(Origin::Signed(owner), call).map_err(|e| {
+                eprintln!(
+                    "Scheduled Call Error\n\tBlock Number: {:?}\n\tError: {}",
+                    block_number,
+                    e
+                )
+            });
+            weight = weight.saturating_add(T::EXTRINSIC_WEIGHT);
+        }
+
+        let scheduler: &mut Pallet<T> = runtime.as_mut();
+        if let Some(next_block) = block_number.checked_add(&T::BlockNumber::one()) {
+            for task in tasks {
+                scheduler.place_task(next_block, task);
+            }
+        }
+        weight
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Schedule `call` to be dispatched on behalf of the caller at block `when`. If
+    /// `maybe_periodic` is `Some((period, count))`, it is dispatched `count` more times after that,
+    /// every `period` blocks.
+    #[allow(clippy::boxed_local)]
+    #[call_index(0)]
+    pub fn schedule(
+        runtime: &mut T,
+        caller: T::AccountId,
+        when: T::BlockNumber,
+        maybe_periodic: Option<(T::BlockNumber, u32)>,
+        call: Box<<T as Config>::RuntimeCall>
+    ) -> DispatchResult {
+        let task = Scheduled { name: None, owner: caller, call: *call, maybe_periodic };
+        Self::do_schedule(runtime, when, task)
+    }
+
+    /// Cancel the task at `index` of the agenda of block `when`.
+    #[call_index(1)]
+    pub fn cancel(
+        &mut self,
+        caller: T::AccountId,
+        when: T::BlockNumber,
+        index: u32
+    ) -> DispatchResult {
+        self.do_cancel(&caller, when, index)
+    }
+
+    /// Like `schedule`, but the task is given a unique `name` so it can be cancelled by name.
+    #[allow(clippy::boxed_local)]
+    #[call_index(2)]
+    pub fn schedule_named(
+        runtime: &mut T,
+        caller: T::AccountId,
+        name: TaskName,
+        when: T::BlockNumber,
+        maybe_periodic: Option<(T::BlockNumber, u32)>,
+        call: Box<<T as Config>::RuntimeCall>
+    ) -> DispatchResult {
+        let task = Scheduled { name: Some(name), owner: caller, call: *call, maybe_periodic };
+        Self::do_schedule(runtime, when, task)
+    }
+
+    /// Cancel the task named `name`.
+    #[call_index(3)]
+    pub fn cancel_named(&mut self, caller: T::AccountId, name: TaskName) -> DispatchResult {
+        let (when, index) = self.lookup(&name).ok_or("Task does not exist")?;
+        self.do_cancel(&caller, when, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pallet;
+    use crate::support::Hooks;
+    use crate::testing::{ self, next_block };
+    use crate::{ balances, system, Runtime, RuntimeCall };
+
+    fn transfer(to: &str, amount: u128) -> Box<RuntimeCall> {
+        Box::new(
+            RuntimeCall::balances(balances::Call::transfer { to: to.to_string(), amount })
+        )
+    }
+
+    #[test]
+    fn schedule_and_cancel() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(
+            Pallet::schedule(&mut runtime, alice.clone(), 0, None, transfer("bob", 10)),
+            Err("Cannot schedule a call in the past")
+        );
+        assert_eq!(
+            Pallet::schedule(&mut runtime, alice.clone(), 2, None, transfer("bob", 10)),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::schedule(&mut runtime, alice.clone(), 2, None, transfer("bob", 20)),
+            Ok(())
+        );
+
+        // Only the owner of a task can cancel it.
+        assert_eq!(
+            runtime.scheduler.cancel(bob.clone(), 2, 1),
+            Err("You are not the owner of this task")
+        );
+        assert_eq!(runtime.scheduler.cancel(alice.clone(), 2, 1), Ok(()));
+        assert_eq!(runtime.scheduler.cancel(alice.clone(), 2, 1), Err("Task does not exist"));
+
+        next_block(&mut runtime);
+        assert_eq!(runtime.balances.balance(&bob), 0);
+        next_block(&mut runtime);
+        assert_eq!(runtime.balances.balance(&bob), 10);
+        assert!(runtime.scheduler.agenda(&2).is_empty());
+    }
+
+    #[test]
+    fn periodic_named_task() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let name = "payroll".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(
+            Pallet::schedule_named(
+                &mut runtime,
+                alice.clone(),
+                name.clone(),
+                1,
+                Some((2, 2)),
+                transfer("bob", 10)
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::schedule_named(
+                &mut runtime,
+                alice.clone(),
+                name.clone(),
+                3,
+                None,
+                transfer("bob", 10)
+            ),
+            Err("A task with this name is already scheduled")
+        );
+
+        // The task runs at blocks 1, 3 and 5.
+        for _ in 0..5 {
+            next_block(&mut runtime);
+        }
+        assert_eq!(runtime.balances.balance(&bob), 30);
+        assert_eq!(runtime.scheduler.lookup(&name), None);
+
+        // A named task can be cancelled by its name before it runs.
+        assert_eq!(
+            Pallet::schedule_named(
+                &mut runtime,
+                alice.clone(),
+                name.clone(),
+                7,
+                Some((1, 5)),
+                transfer("bob", 10)
+            ),
+            Ok(())
+        );
+        assert_eq!(runtime.scheduler.lookup(&name), Some((7, 0)));
+        assert_eq!(runtime.scheduler.cancel_named(alice, name), Ok(()));
+        for _ in 0..5 {
+            next_block(&mut runtime);
+        }
+        assert_eq!(runtime.balances.balance(&bob), 30);
+    }
+
+    #[test]
+    fn tasks_over_the_block_weight_are_deferred() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        for amount in [10, 20, 30] {
+            assert_eq!(
+                Pallet::schedule(&mut runtime, alice.clone(), 1, None, transfer("bob", amount)),
+                Ok(())
+            );
+        }
+
+        // Only two tasks fit in the weight left in block 1, so the last one runs in block 2.
+        runtime.system.inc_block_number();
+        runtime.system.register_weight(<Runtime as system::Config>::MAX_BLOCK_WEIGHT - 25);
+        assert_eq!(Pallet::on_initialize(&mut runtime, 1), 20);
+        assert_eq!(runtime.balances.balance(&bob), 30);
+        assert_eq!(runtime.scheduler.agenda(&2).len(), 1);
+
+        next_block(&mut runtime);
+        assert_eq!(runtime.balances.balance(&bob), 60);
+    }
+
+    #[test]
+    fn execute_block_runs_scheduler() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        assert_eq!(
+            Pallet::schedule(&mut runtime, alice.clone(), 1, None, transfer("bob", 10)),
+            Ok(())
+        );
+
+        // The scheduled transfer happens before the extrinsics of the block, so alice cannot
+        // transfer all of her balance anymore.
+        let block = testing::block(1, 6_000, vec![
+            crate::types::Extrinsic::signed(alice, *transfer("bob", 100))
+        ]);
+        assert_eq!(runtime.execute_block(block), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 10);
+        assert_eq!(runtime.system.block_weight(), 30);
+    }
+}
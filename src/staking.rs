@@ -0,0 +1,533 @@
+use std::collections::BTreeMap;
+use num::traits::{ CheckedAdd, CheckedDiv, CheckedMul, Saturating, Zero };
+use crate::{ balances, metadata::ConstantMetadata, support::{ DispatchResult, Weight } };
+use crate::storage::{ Decode, Encode, StorageMap, StorageValue };
+
+/// The id of the lock this pallet puts on bonded funds in `balances`.
+const STAKING_ID: balances::LockIdentifier = *b"staking ";
+
+/// The index of an era, a number of blocks during which the same validators are elected.
+pub type EraIndex = u32;
+
+pub trait Config: Sized +
+    crate::balances::Config +
+    crate::treasury::Config +
+    AsMut<crate::system::Pallet<Self>> +
+    AsMut<crate::balances::Pallet<Self>> +
+    AsMut<Pallet<Self>> {
+    /// The number of blocks in an era.
+    const BLOCKS_PER_ERA: Self::BlockNumber;
+    /// The number of validators elected for each era.
+    const VALIDATOR_COUNT: usize;
+    /// The number of eras unbonded funds stay locked before they can be withdrawn.
+    const BONDING_DURATION: EraIndex;
+    /// The reward minted for each elected validator at the end of an era, which is shared with its
+    /// nominators in proportion to their stake.
+    const REWARD_PER_VALIDATOR: Self::Balance;
+}
+
+/// Funds being unbonded, which can be withdrawn once era `era` starts.
+#[derive(Debug)]
+pub struct UnlockChunk<T: Config> {
+    pub value: T::Balance,
+    pub era: EraIndex,
+}
+
+impl<T: Config> Encode for UnlockChunk<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.value.encode_to(dest);
+        self.era.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for UnlockChunk<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let value = Decode::decode(input)?;
+        Some(Self { value, era: Decode::decode(input)? })
+    }
+}
+
+/// The funds bonded by an account.
+#[derive(Debug)]
+pub struct StakingLedger<T: Config> {
+    /// The bonded funds which count towards elections and rewards.
+    pub active: T::Balance,
+    pub unlocking: Vec<UnlockChunk<T>>,
+}
+
+impl<T: Config> StakingLedger<T> {
+    /// All the funds of the ledger, which stay locked until they are withdrawn.
+    pub fn total(&self) -> T::Balance {
+        self.unlocking.iter().fold(self.active, |total, chunk| total.saturating_add(chunk.value))
+    }
+}
+
+impl<T: Config> Encode for StakingLedger<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.active.encode_to(dest);
+        self.unlocking.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for StakingLedger<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let active = Decode::decode(input)?;
+        Some(Self { active, unlocking: Decode::decode(input)? })
+    }
+}
+
+/// The stake backing an elected validator.
+#[derive(Debug)]
+pub struct Exposure<T: Config> {
+    pub total: T::Balance,
+    /// The stake of the validator itself.
+    pub own: T::Balance,
+    /// The stake of each nominator backing the validator.
+    pub others: Vec<(T::AccountId, T::Balance)>,
+}
+
+impl<T: Config> Encode for Exposure<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.total.encode_to(dest);
+        self.own.encode_to(dest);
+        self.others.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Exposure<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let total = Decode::decode(input)?;
+        let own = Decode::decode(input)?;
+        Some(Self { total, own, others: Decode::decode(input)? })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The funds bonded by each account.
+    ledgers: StorageMap<T::AccountId, StakingLedger<T>>,
+    /// The accounts which want to be elected as validators.
+    validators: StorageMap<T::AccountId, bool>,
+    /// The validators each nominator wants to back.
+    nominators: StorageMap<T::AccountId, Vec<T::AccountId>>,
+    /// The index of the current era.
+    current_era: StorageValue<EraIndex>,
+    /// The block the current era started at.
+    era_start: StorageValue<T::BlockNumber>,
+    /// The validators elected for the current era, and the stake backing them.
+    exposures: StorageMap<T::AccountId, Exposure<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn ledger(&self, who: &T::AccountId) -> Option<StakingLedger<T>> {
+        self.ledgers.get(who)
+    }
+
+    pub fn current_era(&self) -> EraIndex {
+        self.current_era.get().unwrap_or(0)
+    }
+
+    /// The validators elected for the current era, and the stake backing them.
+    pub fn exposures(&self) -> BTreeMap<T::AccountId, Exposure<T>> {
+        self.exposures.iter().into_iter().collect()
+    }
+
+    // The bonded funds of `who` which count towards elections.
+    fn active(&self, who: &T::AccountId) -> T::Balance {
+        self.ledgers.get(who).map(|ledger| ledger.active).unwrap_or(T::Balance::zero())
+    }
+
+    // Split the active stake of `nominator` evenly between `targets`.
+    fn nomination_share(&self, nominator: &T::AccountId, targets: usize) -> T::Balance {
+        let targets = T::Balance::from(targets as u32);
+        self.active(nominator).checked_div(&targets).unwrap_or(T::Balance::zero())
+    }
+
+    // Elect the validators with the most stake behind them, where the stake of each nominator is
+    // split evenly between the validators it nominates. Ties are broken by account, so that the
+    // result of the election only depends on the bonds, validators and nominations.
+    fn elect(&self) -> BTreeMap<T::AccountId, Exposure<T>> {
+        let mut scores = self.validators
+            .iter()
+            .into_iter()
+            .map(|(validator, _)| {
+                let active = self.active(&validator);
+                (validator, active)
+            })
+            .filter(|(_, active)| !active.is_zero())
+            .collect::<BTreeMap<_, _>>();
+        let nominators = self.nominators.iter();
+        for (nominator, targets) in &nominators {
+            let targets = targets
+                .iter()
+                .filter(|target| scores.contains_key(target))
+                .collect::<Vec<_>>();
+            let share = self.nomination_share(nominator, targets.len());
+            for target in targets {
+                if let Some(score) = scores.get_mut(target) {
+                    *score = score.saturating_add(share);
+                }
+            }
+        }
+
+        let mut ranked = scores.into_iter().collect::<Vec<_>>();
+        ranked.sort_by(|(a, a_score), (b, b_score)| b_score.cmp(a_score).then(a.cmp(b)));
+        let mut exposures = ranked
+            .into_iter()
+            .take(T::VALIDATOR_COUNT)
+            .map(|(validator, _)| {
+                let own = self.active(&validator);
+                (validator, Exposure { total: own, own, others: vec![] })
+            })
+            .collect::<BTreeMap<_, Exposure<T>>>();
+
+        // The stake of each nominator is only split between its targets which were elected.
+        for (nominator, targets) in &nominators {
+            let targets = targets
+                .iter()
+                .filter(|target| exposures.contains_key(target))
+                .cloned()
+                .collect::<Vec<_>>();
+            let share = self.nomination_share(nominator, targets.len());
+            for target in targets {
+                if let Some(exposure) = exposures.get_mut(&target) {
+                    exposure.total = exposure.total.saturating_add(share);
+                    exposure.others.push((nominator.clone(), share));
+                }
+            }
+        }
+        exposures
+    }
+
+    // Pay the rewards of the validators elected for the ending era, and elect the validators for
+    // the next one.
+    fn rotate_era(runtime: &mut T, block_number: T::BlockNumber) {
+        let staking: &mut Pallet<T> = runtime.as_mut();
+        let mut rewards = vec![];
+        for (validator, exposure) in staking.exposures.iter() {
+            let stakes = core::iter::once((&validator, exposure.own))
+                .chain(exposure.others.iter().map(|(nominator, stake)| (nominator, *stake)));
+            for (who, stake) in stakes {
+                let reward = T::REWARD_PER_VALIDATOR.checked_mul(&stake)
+                    .and_then(|reward| reward.checked_div(&exposure.total))
+                    .unwrap_or(T::Balance::zero());
+                rewards.push((who.clone(), reward));
+            }
+        }
+
+        let current_era = staking.current_era().saturating_add(1);
+        staking.current_era.put(&current_era);
+        staking.era_start.put(&block_number);
+        let exposures = staking.elect();
+        staking.exposures.clear();
+        for (validator, exposure) in &exposures {
+            staking.exposures.insert(validator, exposure);
+        }
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        for (who, reward) in rewards {
+            let _res = balances.mint(&who, reward);
+        }
+    }
+
+    // Update the lock on the funds of `who` to its bonded funds.
+    fn update_lock(runtime: &mut T, who: &T::AccountId) {
+        let staking: &mut Pallet<T> = runtime.as_mut();
+        let total = staking.ledger(who).map(|ledger| ledger.total());
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        match total {
+            Some(total) if !total.is_zero() => balances.set_lock(STAKING_ID, who, total),
+            _ => balances.remove_lock(STAKING_ID, who),
+        }
+    }
+
+    /// Slash up to `amount` of the bonded funds of `who`, starting with its active funds and then
+    /// the funds being unbonded. Slashed funds go to the treasury. Returns the amount which was
+    /// slashed.
+    pub fn slash(runtime: &mut T, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let staking: &mut Pallet<T> = runtime.as_mut();
+        let Some(total) = staking.ledger(who).map(|ledger| ledger.total()) else {
+            return T::Balance::zero();
+        };
+
+        // The funds are taken first, so the ledger is only reduced by what was actually taken. They
+        // are burned from `who` even though they are locked, and minted in the treasury.
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        let slashed = balances.burn(who, amount.min(total));
+        let treasury = crate::treasury::Pallet::<T>::account_id();
+        if let Err(e) = balances.mint(&treasury, slashed) {
+            eprintln!("Slash Error\n\tFunds are burned instead of going to the treasury: {}", e);
+        }
+
+        let staking: &mut Pallet<T> = runtime.as_mut();
+        if let Some(mut ledger) = staking.ledger(who) {
+            let mut remaining = slashed;
+            let from_active = remaining.min(ledger.active);
+            ledger.active = ledger.active - from_active;
+            remaining = remaining - from_active;
+            for chunk in ledger.unlocking.iter_mut().rev() {
+                let from_chunk = remaining.min(chunk.value);
+                chunk.value = chunk.value - from_chunk;
+                remaining = remaining - from_chunk;
+            }
+            ledger.unlocking.retain(|chunk| !chunk.value.is_zero());
+            staking.ledgers.insert(who, &ledger);
+        }
+        Self::update_lock(runtime, who);
+        slashed
+    }
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("BLOCKS_PER_ERA", T::BLOCKS_PER_ERA),
+            ConstantMetadata::new("VALIDATOR_COUNT", T::VALIDATOR_COUNT),
+            ConstantMetadata::new("BONDING_DURATION", T::BONDING_DURATION),
+            ConstantMetadata::new("REWARD_PER_VALIDATOR", T::REWARD_PER_VALIDATOR)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {
+    // Start a new era every `BLOCKS_PER_ERA` blocks.
+    fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) -> Weight {
+        let staking: &mut Pallet<T> = runtime.as_mut();
+        let era_start = staking.era_start.get().unwrap_or(T::BlockNumber::zero());
+        let era_end = era_start.checked_add(&T::BLOCKS_PER_ERA);
+        if era_end.is_some_and(|era_end| block_number >= era_end) {
+            Self::rotate_era(runtime, block_number);
+        }
+        0
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Bond `value` of the funds of the caller, which locks them so they can be used to validate
+    /// or nominate. Bonding again adds to the funds already bonded.
+    #[call_index(0)]
+    pub fn bond(runtime: &mut T, caller: T::AccountId, value: T::Balance) -> DispatchResult {
+        if value.is_zero() {
+            return Err("Cannot bond zero");
+        }
+        let staking: &mut Pallet<T> = runtime.as_mut();
+        let total = staking
+            .ledger(&caller)
+            .map(|ledger| ledger.total())
+            .unwrap_or(T::Balance::zero())
+            .checked_add(&value)
+            .ok_or("Overflow")?;
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        if balances.balance(&caller) < total {
+            return Err("Not enough funds to bond");
+        }
+
+        let staking: &mut Pallet<T> = runtime.as_mut();
+        let mut ledger = staking
+            .ledger(&caller)
+            .unwrap_or(StakingLedger { active: T::Balance::zero(), unlocking: vec![] });
+        // The total of the ledger plus `value` did not overflow, so neither can its active funds.
+        ledger.active = ledger.active.saturating_add(value);
+        staking.ledgers.insert(&caller, &ledger);
+        Self::update_lock(runtime, &caller);
+        Ok(())
+    }
+
+    /// Start unbonding `value` of the bonded funds of the caller. They stop counting towards
+    /// elections right away, and can be withdrawn after `BONDING_DURATION` eras.
+    #[call_index(1)]
+    pub fn unbond(&mut self, caller: T::AccountId, value: T::Balance) -> DispatchResult {
+        let era = self.current_era().checked_add(T::BONDING_DURATION).ok_or("Overflow")?;
+        let mut ledger = self.ledger(&caller).ok_or("This account is not bonded")?;
+        if value.is_zero() || value > ledger.active {
+            return Err("Not enough bonded funds");
+        }
+        ledger.active = ledger.active - value;
+        ledger.unlocking.push(UnlockChunk { value, era });
+        self.ledgers.insert(&caller, &ledger);
+        Ok(())
+    }
+
+    /// Withdraw the funds of the caller which have finished unbonding, unlocking them.
+    #[call_index(2)]
+    pub fn withdraw_unbonded(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+        let staking: &mut Pallet<T> = runtime.as_mut();
+        let current_era = staking.current_era();
+        let mut ledger = staking.ledger(&caller).ok_or("This account is not bonded")?;
+        ledger.unlocking.retain(|chunk| chunk.era > current_era);
+        if ledger.total().is_zero() {
+            staking.ledgers.remove(&caller);
+            staking.validators.remove(&caller);
+            staking.nominators.remove(&caller);
+        } else {
+            staking.ledgers.insert(&caller, &ledger);
+        }
+        Self::update_lock(runtime, &caller);
+        Ok(())
+    }
+
+    /// Declare the caller wants to be elected as a validator from the next era.
+    #[call_index(3)]
+    pub fn validate(&mut self, caller: T::AccountId) -> DispatchResult {
+        if self.ledger(&caller).is_none() {
+            return Err("This account is not bonded");
+        }
+        self.nominators.remove(&caller);
+        self.validators.insert(&caller, &true);
+        Ok(())
+    }
+
+    /// Declare the caller wants to back `targets` with its bonded funds from the next era.
+    #[call_index(4)]
+    pub fn nominate(&mut self, caller: T::AccountId, targets: Vec<T::AccountId>) -> DispatchResult {
+        if self.ledger(&caller).is_none() {
+            return Err("This account is not bonded");
+        }
+        if targets.is_empty() {
+            return Err("Nominations need at least one target");
+        }
+        self.validators.remove(&caller);
+        self.nominators.insert(&caller, &targets);
+        Ok(())
+    }
+
+    /// Stop validating or nominating from the next era.
+    #[call_index(5)]
+    pub fn chill(&mut self, caller: T::AccountId) -> DispatchResult {
+        let validating = self.validators.get(&caller).is_some();
+        let nominating = self.nominators.get(&caller).is_some();
+        if !validating && !nominating {
+            return Err("This account is not validating or nominating");
+        }
+        self.validators.remove(&caller);
+        self.nominators.remove(&caller);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pallet;
+    use crate::storage::{ FileBackend, Store };
+    use crate::{ testing::run_to_block, treasury, Runtime };
+
+    #[test]
+    fn bond_unbond_and_withdraw() {
+        let alice = "alice".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(Pallet::bond(&mut runtime, alice.clone(), 101), Err("Not enough funds to bond"));
+        assert_eq!(Pallet::bond(&mut runtime, alice.clone(), 60), Ok(()));
+        assert_eq!(Pallet::bond(&mut runtime, alice.clone(), 20), Ok(()));
+        assert_eq!(runtime.balances.locked(&alice), 80);
+
+        assert_eq!(runtime.staking.unbond(alice.clone(), 81), Err("Not enough bonded funds"));
+        assert_eq!(runtime.staking.unbond(alice.clone(), 80), Ok(()));
+        assert_eq!(runtime.staking.ledger(&alice).unwrap().active, 0);
+
+        // Unbonded funds stay locked for 2 eras.
+        run_to_block(&mut runtime, 5);
+        assert_eq!(runtime.staking.current_era(), 1);
+        assert_eq!(Pallet::withdraw_unbonded(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.balances.locked(&alice), 80);
+
+        run_to_block(&mut runtime, 10);
+        assert_eq!(Pallet::withdraw_unbonded(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.balances.locked(&alice), 0);
+        assert!(runtime.staking.ledger(&alice).is_none());
+    }
+
+    #[test]
+    fn election_and_rewards() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let charlie = "charlie".to_string();
+        let dave = "dave".to_string();
+
+        let mut runtime = Runtime::new();
+        for (who, value) in [(&alice, 100), (&bob, 50), (&charlie, 50), (&dave, 40)] {
+            runtime.balances.set_balance(who, value);
+            assert_eq!(Pallet::bond(&mut runtime, who.clone(), value), Ok(()));
+        }
+        assert_eq!(runtime.staking.validate(alice.clone()), Ok(()));
+        assert_eq!(runtime.staking.validate(bob.clone()), Ok(()));
+        assert_eq!(runtime.staking.validate(charlie.clone()), Ok(()));
+        assert_eq!(runtime.staking.nominate(dave.clone(), vec![charlie.clone()]), Ok(()));
+
+        // Charlie is backed by dave, so it is elected over bob.
+        run_to_block(&mut runtime, 5);
+        let elected = runtime.staking.exposures().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(elected, vec![alice.clone(), charlie.clone()]);
+        assert_eq!(runtime.staking.exposures()[&charlie].total, 90);
+
+        // Each elected validator earns 10 at the end of the era, shared with its nominators.
+        run_to_block(&mut runtime, 10);
+        assert_eq!(runtime.balances.balance(&alice), 110);
+        assert_eq!(runtime.balances.balance(&bob), 50);
+        assert_eq!(runtime.balances.balance(&charlie), 55);
+        assert_eq!(runtime.balances.balance(&dave), 44);
+    }
+
+    #[test]
+    fn state_survives_reopening_the_store() {
+        let alice = "alice".to_string();
+        let path = std::env::temp_dir().join(format!("staking-{}.log", std::process::id()));
+
+        let store = Store::with_backend(FileBackend::open(&path).unwrap());
+        let mut runtime = Runtime::with_store(store);
+        runtime.balances.set_balance(&alice, 100);
+        assert_eq!(Pallet::bond(&mut runtime, alice.clone(), 60), Ok(()));
+        assert_eq!(runtime.staking.validate(alice.clone()), Ok(()));
+        run_to_block(&mut runtime, 5);
+        drop(runtime);
+
+        let store = Store::with_backend(FileBackend::open(&path).unwrap());
+        let runtime = Runtime::with_store(store);
+        assert_eq!(runtime.system.block_number(), 5);
+        assert_eq!(runtime.staking.current_era(), 1);
+        assert_eq!(runtime.staking.ledger(&alice).unwrap().active, 60);
+        assert_eq!(runtime.staking.exposures()[&alice].own, 60);
+        assert_eq!(runtime.balances.locked(&alice), 60);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn slash_bonded_funds() {
+        let alice = "alice".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        assert_eq!(Pallet::bond(&mut runtime, alice.clone(), 80), Ok(()));
+        assert_eq!(runtime.staking.unbond(alice.clone(), 30), Ok(()));
+
+        // Active funds are slashed first, then the funds being unbonded.
+        assert_eq!(Pallet::slash(&mut runtime, &alice, 60), 60);
+        let ledger = runtime.staking.ledger(&alice).unwrap();
+        assert_eq!(ledger.active, 0);
+        assert_eq!(ledger.total(), 20);
+        assert_eq!(runtime.balances.balance(&alice), 40);
+        assert_eq!(runtime.balances.locked(&alice), 20);
+        // The slashed funds feed the treasury.
+        assert_eq!(runtime.balances.balance(&treasury::Pallet::<Runtime>::account_id()), 60);
+
+        assert_eq!(Pallet::slash(&mut runtime, &alice, 100), 20);
+        assert_eq!(runtime.balances.balance(&alice), 20);
+        assert_eq!(runtime.balances.balance(&treasury::Pallet::<Runtime>::account_id()), 80);
+        assert_eq!(runtime.balances.locked(&alice), 0);
+
+        // The ledger is only reduced by the funds which could be taken.
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&bob, 100);
+        assert_eq!(Pallet::bond(&mut runtime, bob.clone(), 80), Ok(()));
+        runtime.balances.set_balance(&bob, 30);
+        assert_eq!(Pallet::slash(&mut runtime, &bob, 50), 30);
+        assert_eq!(runtime.staking.ledger(&bob).unwrap().total(), 50);
+        assert_eq!(runtime.balances.balance(&bob), 0);
+    }
+}
@@ -0,0 +1,783 @@
+use core::{ any::type_name, fmt::Debug, marker::PhantomData };
+use std::{ cell::RefCell, collections::BTreeMap, fs::File, path::Path, rc::Rc };
+use std::io::{ Read, Write };
+use crate::metadata::StorageEntryType;
+
+/// Types which can be written to storage as bytes.
+pub trait Encode {
+    fn encode_to(&self, dest: &mut Vec<u8>);
+
+    fn encode(&self) -> Vec<u8> {
+        let mut dest = Vec::new();
+        self.encode_to(&mut dest);
+        dest
+    }
+}
+
+/// Types which can be read back from the bytes written by `Encode`.
+pub trait Decode: Sized {
+    /// Read a value from the start of `input`, and move `input` past it.
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+// Read the next `len` bytes of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Some(bytes)
+}
+
+macro_rules! impl_codec_for_int {
+    ($($int:ty),*) => {
+        $(
+            impl Encode for $int {
+                fn encode_to(&self, dest: &mut Vec<u8>) {
+                    dest.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+
+            impl Decode for $int {
+                fn decode(input: &mut &[u8]) -> Option<Self> {
+                    let bytes = take(input, core::mem::size_of::<$int>())?;
+                    Some(<$int>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_for_int!(u8, u16, u32, u64, u128);
+
+impl Encode for bool {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl<const N: usize> Encode for [u8; N] {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.extend_from_slice(self);
+    }
+}
+
+impl<const N: usize> Decode for [u8; N] {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        take(input, N)?.try_into().ok()
+    }
+}
+
+// Strings and vectors are prefixed with their length.
+impl Encode for str {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (self.len() as u32).encode_to(dest);
+        dest.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl<E: Encode + ?Sized> Encode for &E {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (**self).encode_to(dest);
+    }
+}
+
+impl<E: Encode + ?Sized> Encode for Box<E> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (**self).encode_to(dest);
+    }
+}
+
+impl<D: Decode> Decode for Box<D> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        D::decode(input).map(Box::new)
+    }
+}
+
+impl Encode for String {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_str().encode_to(dest);
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = u32::decode(input)? as usize;
+        String::from_utf8(take(input, len)?.to_vec()).ok()
+    }
+}
+
+impl<E: Encode> Encode for Vec<E> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (self.len() as u32).encode_to(dest);
+        for item in self {
+            item.encode_to(dest);
+        }
+    }
+}
+
+impl<D: Decode> Decode for Vec<D> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = u32::decode(input)?;
+        (0..len).map(|_| D::decode(input)).collect()
+    }
+}
+
+impl<E: Encode> Encode for Option<E> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            None => dest.push(0),
+            Some(value) => {
+                dest.push(1);
+                value.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<D: Decode> Decode for Option<D> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(None),
+            1 => Some(Some(D::decode(input)?)),
+            _ => None,
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+        self.1.encode_to(dest);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+/// A 64 bit FNV-1a hash. This is not a cryptographic hash, but it is enough to spread keys and
+/// compare the contents of stores.
+pub fn hash(data: &[u8]) -> [u8; 8] {
+    let hash = data.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    hash.to_be_bytes()
+}
+
+// Keys are stored as their hash followed by their encoding, so they are spread evenly while still
+// being readable when iterating.
+fn hashed_key_to(key: &impl Encode, dest: &mut Vec<u8>) {
+    let encoded = key.encode();
+    dest.extend_from_slice(&hash(&encoded));
+    dest.extend_from_slice(&encoded);
+}
+
+// Read a key written by `hashed_key_to`.
+fn decode_hashed_key<K: Decode>(input: &mut &[u8]) -> Option<K> {
+    take(input, 8)?;
+    K::decode(input)
+}
+
+// The prefix of all keys of a storage item: the hash of its pallet followed by its own hash.
+fn item_prefix(pallet: &str, item: &str) -> Vec<u8> {
+    [hash(pallet.as_bytes()), hash(item.as_bytes())].concat()
+}
+
+/// Where the key-value pairs of a `Store` are kept.
+pub trait Backend {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+
+    fn put(&mut self, key: Vec<u8>, value: Vec<u8>);
+
+    fn delete(&mut self, key: &[u8]);
+
+    /// All the key-value pairs whose key starts with `prefix`, in order.
+    fn iter_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)>;
+
+    /// Make the changes since the last commit durable. The runtime commits after each block.
+    fn commit(&mut self) -> std::io::Result<()>;
+}
+
+// The pairs of a map whose key starts with `prefix`.
+fn iter_map_prefix(data: &BTreeMap<Vec<u8>, Vec<u8>>, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    data.range(prefix.to_vec()..)
+        .take_while(|(key, _)| key.starts_with(prefix))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// A backend keeping everything in memory, which is lost when the process exits.
+#[derive(Default)]
+pub struct InMemoryBackend {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Backend for InMemoryBackend {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.get(key).cloned()
+    }
+
+    fn put(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.data.insert(key, value);
+    }
+
+    fn delete(&mut self, key: &[u8]) {
+        self.data.remove(key);
+    }
+
+    fn iter_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        iter_map_prefix(&self.data, prefix)
+    }
+
+    fn commit(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A backend keeping everything in memory, and writing the changes to an append-only log file on
+/// each commit. Opening the file again replays the log, so a node can resume from its last block.
+///
+/// Each commit writes its changes followed by a commit marker, and only the changes of complete
+/// commits are replayed. Whatever follows the last marker, like a commit cut short by a crash, is
+/// removed from the file when it is opened.
+pub struct FileBackend {
+    file: File,
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+    // The records of the changes since the last commit.
+    pending: Vec<u8>,
+}
+
+// The records of the log: a tag, then the key for puts and deletes, and the value for puts.
+const PUT: u8 = 0;
+const DELETE: u8 = 1;
+const COMMIT: u8 = 2;
+
+impl FileBackend {
+    /// Open the log at `path`, creating it if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut file = File::options().read(true).append(true).create(true).open(path)?;
+        let mut log = Vec::new();
+        file.read_to_end(&mut log)?;
+
+        // Replay the log, applying the changes of each commit once its marker is read. `end` is
+        // the length of the log up to the last marker.
+        let mut data = BTreeMap::new();
+        let mut changes = Vec::new();
+        let mut input = log.as_slice();
+        let mut end = 0;
+        while let Some(tag) = u8::decode(&mut input) {
+            let change = match tag {
+                PUT => Vec::<u8>::decode(&mut input).zip(Vec::<u8>::decode(&mut input)),
+                DELETE => Vec::<u8>::decode(&mut input).map(|key| (key, Vec::new())),
+                COMMIT => {
+                    for (tag, key, value) in changes.drain(..) {
+                        if tag == PUT {
+                            data.insert(key, value);
+                        } else {
+                            data.remove(&key);
+                        }
+                    }
+                    end = log.len() - input.len();
+                    continue;
+                }
+                _ => {
+                    let offset = log.len() - input.len() - 1;
+                    let msg = format!("unknown record tag {} at offset {}", tag, offset);
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg));
+                }
+            };
+            // A record cut short by a crash while writing it ends the log.
+            let Some((key, value)) = change else {
+                break;
+            };
+            changes.push((tag, key, value));
+        }
+        file.set_len(end as u64)?;
+        Ok(Self { file, data, pending: Vec::new() })
+    }
+}
+
+impl Backend for FileBackend {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.get(key).cloned()
+    }
+
+    fn put(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        PUT.encode_to(&mut self.pending);
+        key.encode_to(&mut self.pending);
+        value.encode_to(&mut self.pending);
+        self.data.insert(key, value);
+    }
+
+    fn delete(&mut self, key: &[u8]) {
+        DELETE.encode_to(&mut self.pending);
+        key.to_vec().encode_to(&mut self.pending);
+        self.data.remove(key);
+    }
+
+    fn iter_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        iter_map_prefix(&self.data, prefix)
+    }
+
+    fn commit(&mut self) -> std::io::Result<()> {
+        COMMIT.encode_to(&mut self.pending);
+        self.file.write_all(&self.pending)?;
+        self.file.sync_data()?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+// Changes to the keys of a store, where `None` is a removed key.
+type Changes = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// A key-value store, shared by all the storage items of a runtime. Cloning it gives another
+/// handle to the same store.
+///
+/// Changes are kept apart from the backend until they are committed, so they can be reverted.
+#[derive(Clone)]
+pub struct Store {
+    backend: Rc<RefCell<dyn Backend>>,
+    // The changes since the last commit.
+    changes: Rc<RefCell<Changes>>,
+}
+
+impl Store {
+    /// A store kept in memory.
+    pub fn new() -> Self {
+        Self::with_backend(InMemoryBackend::default())
+    }
+
+    pub fn with_backend(backend: impl Backend + 'static) -> Self {
+        Self { backend: Rc::new(RefCell::new(backend)), changes: Default::default() }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.changes.borrow().get(key) {
+            Some(change) => change.clone(),
+            None => self.backend.borrow().get(key),
+        }
+    }
+
+    pub fn insert(&self, key: Vec<u8>, value: Vec<u8>) {
+        self.changes.borrow_mut().insert(key, Some(value));
+    }
+
+    pub fn remove(&self, key: &[u8]) {
+        self.changes.borrow_mut().insert(key.to_vec(), None);
+    }
+
+    /// All the key-value pairs whose key starts with `prefix`, in order.
+    pub fn iter_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut pairs = self.backend
+            .borrow()
+            .iter_prefix(prefix)
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let changes = self.changes.borrow();
+        let changes = changes
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix));
+        for (key, change) in changes {
+            match change {
+                Some(value) => pairs.insert(key.clone(), value.clone()),
+                None => pairs.remove(key),
+            };
+        }
+        pairs.into_iter().collect()
+    }
+
+    /// Write the changes since the last commit to the backend, and make them durable.
+    pub fn commit(&self) -> std::io::Result<()> {
+        let mut backend = self.backend.borrow_mut();
+        for (key, change) in core::mem::take(&mut *self.changes.borrow_mut()) {
+            match change {
+                Some(value) => backend.put(key, value),
+                None => backend.delete(&key),
+            }
+        }
+        backend.commit()
+    }
+
+    /// Discard the changes since the last commit.
+    pub fn revert(&self) {
+        self.changes.borrow_mut().clear();
+    }
+
+    /// A hash of the whole store. Two stores with the same contents have the same root.
+    pub fn root(&self) -> [u8; 8] {
+        hash(&self.iter_prefix(&[]).encode())
+    }
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Store {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Store").field("keys", &self.iter_prefix(&[]).len()).finish()
+    }
+}
+
+/// A single value in storage.
+pub struct StorageValue<V> {
+    store: Store,
+    key: Vec<u8>,
+    _value: PhantomData<V>,
+}
+
+impl<V: Encode + Decode> StorageValue<V> {
+    pub fn new(store: &Store, pallet: &str, item: &str) -> Self {
+        Self { store: store.clone(), key: item_prefix(pallet, item), _value: PhantomData }
+    }
+
+    /// The store this item is kept in.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    pub fn get(&self) -> Option<V> {
+        self.store.get(&self.key).and_then(|value| V::decode(&mut value.as_slice()))
+    }
+
+    pub fn put(&mut self, value: &V) {
+        self.store.insert(self.key.clone(), value.encode());
+    }
+
+    pub fn kill(&mut self) {
+        self.store.remove(&self.key);
+    }
+}
+
+impl<V: Encode + Decode + Debug> Debug for StorageValue<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl<V> StorageEntryType for StorageValue<V> {
+    const KIND: &'static str = "StorageValue";
+
+    fn keys() -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    fn value() -> &'static str {
+        type_name::<V>()
+    }
+}
+
+/// A map in storage, from keys of type `K` to values of type `V`.
+pub struct StorageMap<K, V> {
+    store: Store,
+    prefix: Vec<u8>,
+    _types: PhantomData<(K, V)>,
+}
+
+impl<K: Encode + Decode, V: Encode + Decode> StorageMap<K, V> {
+    pub fn new(store: &Store, pallet: &str, item: &str) -> Self {
+        Self { store: store.clone(), prefix: item_prefix(pallet, item), _types: PhantomData }
+    }
+
+    fn key(&self, key: &K) -> Vec<u8> {
+        let mut dest = self.prefix.clone();
+        hashed_key_to(key, &mut dest);
+        dest
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.store.get(&self.key(key)).and_then(|value| V::decode(&mut value.as_slice()))
+    }
+
+    pub fn insert(&mut self, key: &K, value: &V) {
+        self.store.insert(self.key(key), value.encode());
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        self.store.remove(&self.key(key));
+    }
+
+    /// Remove all the entries of the map.
+    pub fn clear(&mut self) {
+        for (key, _) in self.store.iter_prefix(&self.prefix) {
+            self.store.remove(&key);
+        }
+    }
+
+    /// All the entries of the map, in the order of their hashed keys.
+    pub fn iter(&self) -> Vec<(K, V)> {
+        self.store
+            .iter_prefix(&self.prefix)
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let key = decode_hashed_key(&mut &key[self.prefix.len()..])?;
+                Some((key, V::decode(&mut value.as_slice())?))
+            })
+            .collect()
+    }
+}
+
+impl<K, V> Debug for StorageMap<K, V>
+    where K: Encode + Decode + Debug, V: Encode + Decode + Debug
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> StorageEntryType for StorageMap<K, V> {
+    const KIND: &'static str = "StorageMap";
+
+    fn keys() -> Vec<&'static str> {
+        vec![type_name::<K>()]
+    }
+
+    fn value() -> &'static str {
+        type_name::<V>()
+    }
+}
+
+/// A map in storage with two keys, so all the entries of the first key can be iterated or
+/// removed together.
+pub struct StorageDoubleMap<K1, K2, V> {
+    store: Store,
+    prefix: Vec<u8>,
+    _types: PhantomData<(K1, K2, V)>,
+}
+
+impl<K1, K2, V> StorageDoubleMap<K1, K2, V>
+    where K1: Encode + Decode, K2: Encode + Decode, V: Encode + Decode
+{
+    pub fn new(store: &Store, pallet: &str, item: &str) -> Self {
+        Self { store: store.clone(), prefix: item_prefix(pallet, item), _types: PhantomData }
+    }
+
+    fn first_key(&self, key1: &K1) -> Vec<u8> {
+        let mut dest = self.prefix.clone();
+        hashed_key_to(key1, &mut dest);
+        dest
+    }
+
+    fn key(&self, key1: &K1, key2: &K2) -> Vec<u8> {
+        let mut dest = self.first_key(key1);
+        hashed_key_to(key2, &mut dest);
+        dest
+    }
+
+    pub fn get(&self, key1: &K1, key2: &K2) -> Option<V> {
+        self.store.get(&self.key(key1, key2)).and_then(|value| V::decode(&mut value.as_slice()))
+    }
+
+    pub fn insert(&mut self, key1: &K1, key2: &K2, value: &V) {
+        self.store.insert(self.key(key1, key2), value.encode());
+    }
+
+    pub fn remove(&mut self, key1: &K1, key2: &K2) {
+        self.store.remove(&self.key(key1, key2));
+    }
+
+    /// All the entries under `key1`, by their second key.
+    pub fn iter_prefix(&self, key1: &K1) -> Vec<(K2, V)> {
+        let prefix = self.first_key(key1);
+        self.store
+            .iter_prefix(&prefix)
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let key2 = decode_hashed_key(&mut &key[prefix.len()..])?;
+                Some((key2, V::decode(&mut value.as_slice())?))
+            })
+            .collect()
+    }
+
+    /// All the entries of the map.
+    pub fn iter(&self) -> Vec<(K1, K2, V)> {
+        self.store
+            .iter_prefix(&self.prefix)
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let mut key = &key[self.prefix.len()..];
+                let key1 = decode_hashed_key(&mut key)?;
+                let key2 = decode_hashed_key(&mut key)?;
+                Some((key1, key2, V::decode(&mut value.as_slice())?))
+            })
+            .collect()
+    }
+}
+
+impl<K1, K2, V> Debug for StorageDoubleMap<K1, K2, V>
+    where
+        K1: Encode + Decode + Debug,
+        K2: Encode + Decode + Debug,
+        V: Encode + Decode + Debug
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let entries = self.iter().into_iter().map(|(key1, key2, value)| ((key1, key2), value));
+        f.debug_map().entries(entries).finish()
+    }
+}
+
+impl<K1, K2, V> StorageEntryType for StorageDoubleMap<K1, K2, V> {
+    const KIND: &'static str = "StorageDoubleMap";
+
+    fn keys() -> Vec<&'static str> {
+        vec![type_name::<K1>(), type_name::<K2>()]
+    }
+
+    fn value() -> &'static str {
+        type_name::<V>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ Decode, Encode, FileBackend, StorageDoubleMap, StorageMap, StorageValue, Store };
+
+    #[test]
+    fn codec_round_trip() {
+        let value = (vec![Some("alice".to_string()), None], (7u32, *b"staking "));
+        let encoded = value.encode();
+        assert_eq!(Decode::decode(&mut encoded.as_slice()), Some(value));
+        assert_eq!(u32::decode(&mut [1u8, 0].as_slice()), None);
+    }
+
+    #[test]
+    fn storage_items_share_a_store() {
+        let store = Store::new();
+        let mut value = StorageValue::<u32>::new(&store, "system", "block_number");
+        let mut map = StorageMap::<String, u128>::new(&store, "balances", "balances");
+        let mut double_map = StorageDoubleMap::<String, u8, bool>::new(&store, "pallet", "item");
+
+        value.put(&5);
+        map.insert(&"alice".to_string(), &100);
+        map.insert(&"bob".to_string(), &50);
+        double_map.insert(&"alice".to_string(), &1, &true);
+        double_map.insert(&"alice".to_string(), &2, &false);
+        double_map.insert(&"bob".to_string(), &1, &true);
+
+        assert_eq!(value.get(), Some(5));
+        assert_eq!(map.get(&"alice".to_string()), Some(100));
+        assert_eq!(map.iter().len(), 2);
+        assert_eq!(double_map.iter_prefix(&"alice".to_string()).len(), 2);
+        assert_eq!(store.iter_prefix(&[]).len(), 6);
+
+        // The root changes with the contents of the store.
+        let root = store.root();
+        map.remove(&"bob".to_string());
+        assert_eq!(map.iter(), vec![("alice".to_string(), 100)]);
+        assert_ne!(store.root(), root);
+        value.kill();
+        assert_eq!(value.get(), None);
+    }
+
+    #[test]
+    fn uncommitted_changes_can_be_reverted() {
+        let store = Store::new();
+        let mut value = StorageValue::<u32>::new(&store, "system", "block_number");
+        let mut map = StorageMap::<String, u128>::new(&store, "balances", "balances");
+        value.put(&5);
+        map.insert(&"alice".to_string(), &100);
+        store.commit().unwrap();
+        let root = store.root();
+
+        value.kill();
+        map.insert(&"alice".to_string(), &50);
+        map.insert(&"bob".to_string(), &50);
+        assert_eq!(map.iter().len(), 2);
+        store.revert();
+        assert_eq!(value.get(), Some(5));
+        assert_eq!(map.iter(), vec![("alice".to_string(), 100)]);
+        assert_eq!(store.root(), root);
+    }
+
+    #[test]
+    fn pallets_share_the_runtime_store() {
+        let mut runtime = crate::Runtime::new();
+        let root = runtime.system.storage().root();
+
+        // Writing to balances changes the store seen from system.
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+        assert_ne!(runtime.system.storage().root(), root);
+
+        // Each runtime has its own store.
+        assert_eq!(crate::Runtime::new().system.storage().root(), root);
+        assert_eq!(crate::Runtime::new().balances.balance(&"alice".to_string()), 0);
+    }
+
+    #[test]
+    fn resume_from_file_backend() {
+        let alice = "alice".to_string();
+        let path = std::env::temp_dir().join(format!("store-{}.log", std::process::id()));
+        let open = || Store::with_backend(FileBackend::open(&path).unwrap());
+
+        let mut runtime = crate::Runtime::with_store(open());
+        runtime.system.inc_block_number();
+        runtime.balances.set_balance(&alice, 100);
+        runtime.system.storage().commit().unwrap();
+        // Changes which are not committed are lost on restart.
+        runtime.balances.set_balance(&alice, 50);
+        let root = runtime.system.storage().root();
+        drop(runtime);
+
+        let runtime = crate::Runtime::with_store(open());
+        assert_eq!(runtime.system.block_number(), 1);
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert_ne!(runtime.system.storage().root(), root);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn file_backend_replays_complete_commits() {
+        use super::{ Backend, PUT };
+        use std::io::Write;
+
+        let path = std::env::temp_dir().join(format!("commits-{}.log", std::process::id()));
+        let mut backend = FileBackend::open(&path).unwrap();
+        backend.put(b"a".to_vec(), vec![1]);
+        backend.put(b"b".to_vec(), vec![2]);
+        backend.commit().unwrap();
+        backend.delete(b"a");
+        backend.commit().unwrap();
+        drop(backend);
+        let len = std::fs::metadata(&path).unwrap().len();
+
+        // A commit which was written without its marker, followed by a record cut short, is
+        // dropped, and removed from the file.
+        let mut file = std::fs::File::options().append(true).open(&path).unwrap();
+        let mut torn = Vec::new();
+        PUT.encode_to(&mut torn);
+        b"c".to_vec().encode_to(&mut torn);
+        vec![3u8].encode_to(&mut torn);
+        torn.extend_from_slice(&[PUT, 5, 0]);
+        file.write_all(&torn).unwrap();
+        drop(file);
+        let backend = FileBackend::open(&path).unwrap();
+        assert_eq!(backend.iter_prefix(&[]), vec![(b"b".to_vec(), vec![2])]);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+        drop(backend);
+
+        // A record with an unknown tag is an error, rather than being read as another record.
+        let mut file = std::fs::File::options().append(true).open(&path).unwrap();
+        file.write_all(&[9]).unwrap();
+        drop(file);
+        let error = FileBackend::open(&path).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -8,15 +8,367 @@ pub struct Header<BlockNumber> {
 }
 
 pub struct Extrinsic<Caller, Call> {
-    pub caller: Caller,
+    /// The account which signed the extrinsic, or `None` for an unsigned extrinsic, like an
+    /// inherent provided by the block author.
+    pub caller: Option<Caller>,
     pub call: Call,
 }
 
+impl<Caller, Call> Extrinsic<Caller, Call> {
+    /// An extrinsic signed by `caller`, which takes the `Call` of any pallet, or a `RuntimeCall`.
+    pub fn signed(caller: Caller, call: impl Into<Call>) -> Self {
+        Self { caller: Some(caller), call: call.into() }
+    }
+
+    /// An unsigned extrinsic, like an inherent.
+    pub fn unsigned(call: impl Into<Call>) -> Self {
+        Self { caller: None, call: call.into() }
+    }
+}
+
 pub type DispatchResult = Result<(), &'static str>;
 
+/// Who a call is dispatched on behalf of.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin<AccountId> {
+    /// The chain itself, for example when enacting a referendum. Only root can make privileged
+    /// calls.
+    Root,
+    /// An account, for example the caller of an extrinsic.
+    Signed(AccountId),
+    /// Nobody, for an unsigned extrinsic like an inherent.
+    None,
+}
+
+impl<AccountId> Origin<AccountId> {
+    /// Get the account of a signed origin, or fail for any other origin.
+    pub fn ensure_signed(self) -> Result<AccountId, &'static str> {
+        match self {
+            Origin::Signed(who) => Ok(who),
+            Origin::Root | Origin::None => Err("Expected a signed origin"),
+        }
+    }
+
+    /// Fail for any origin other than root.
+    pub fn ensure_root(&self) -> DispatchResult {
+        match self {
+            Origin::Root => Ok(()),
+            Origin::Signed(_) | Origin::None => Err("Expected the root origin"),
+        }
+    }
+
+    /// Fail for any origin other than none.
+    pub fn ensure_none(&self) -> DispatchResult {
+        match self {
+            Origin::None => Ok(()),
+            Origin::Root | Origin::Signed(_) => Err("Expected no origin"),
+        }
+    }
+}
+
+/// Check that an origin is allowed to do something, like approving a treasury spend. This lets the
+/// runtime decide which origin a pallet requires.
+pub trait EnsureOrigin<AccountId> {
+    fn ensure_origin(origin: Origin<AccountId>) -> DispatchResult;
+}
+
+/// An `EnsureOrigin` implementation which only allows root.
+pub struct EnsureRoot;
+
+impl<AccountId> EnsureOrigin<AccountId> for EnsureRoot {
+    fn ensure_origin(origin: Origin<AccountId>) -> DispatchResult {
+        origin.ensure_root()
+    }
+}
+
+/// The id of a pallet, from which the account it owns funds with is derived.
+pub type PalletId = &'static str;
+
+/// An instance of a pallet, so that the same pallet can be included several times in a runtime,
+/// with its own `Config` and storage for each instance. The default instance is `()`.
+pub trait Instance: 'static {
+    /// Added to the storage prefix of the pallet, so that instances do not share storage.
+    const PREFIX: &'static str;
+}
+
+impl Instance for () {
+    const PREFIX: &'static str = "";
+}
+
+/// The second instance of a pallet, after the default instance `()`.
+#[derive(Debug)]
+pub struct Instance2;
+
+impl Instance for Instance2 {
+    const PREFIX: &'static str = "Instance2";
+}
+
+/// Dispatch a call on behalf of a caller. The runtime implements this for its `RuntimeCall`, which
+/// pallets require in their `Config` to dispatch calls themselves, like a scheduler or a proxy.
+///
+/// Such pallets take the call as a `Box<RuntimeCall>` argument of one of their own calls, since
+/// `RuntimeCall` would otherwise contain itself through the `Call` of the pallet. Clippy's
+/// `boxed_local` lint cannot see this, so those functions allow it.
 pub trait Dispatch {
     type Caller;
     type Call;
 
     fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
 }
+
+/// Whether a set includes a value of `T`, for example whether a call is allowed to be dispatched.
+/// The set is decided by the implementation, which is given the runtime `R` to read its state.
+pub trait Contains<R, T> {
+    fn contains(runtime: &R, t: &T) -> bool;
+}
+
+/// The empty filter `()` contains everything, so it allows all calls.
+impl<R, T> Contains<R, T> for () {
+    fn contains(_runtime: &R, _t: &T) -> bool {
+        true
+    }
+}
+
+/// The names of a call, so that calls can be identified by name, like to pause them.
+pub trait GetCallName {
+    /// The name of the pallet of the call, as it is named in the runtime.
+    fn pallet_name(&self) -> &'static str;
+    /// The name of the function called.
+    fn call_name(&self) -> &'static str;
+}
+
+/// A filter over instances of `T`, for example a kind of proxy which only allows a subset of calls.
+pub trait InstanceFilter<T> {
+    /// Returns `true` if `t` passes the filter.
+    fn filter(&self, t: &T) -> bool;
+}
+
+/// Convert from one type to another, for example from a block number to a balance.
+pub trait Convert<A, B> {
+    fn convert(a: A) -> B;
+}
+
+/// A `Convert` implementation which uses `Into`.
+pub struct ConvertInto;
+
+impl<A: Into<B>, B> Convert<A, B> for ConvertInto {
+    fn convert(a: A) -> B {
+        a.into()
+    }
+}
+
+/// The key under which the data for an inherent is stored in `InherentData`.
+pub type InherentIdentifier = &'static str;
+
+/// The data a block author uses to create inherents, like the current time.
+#[derive(Default)]
+pub struct InherentData {
+    data: std::collections::BTreeMap<InherentIdentifier, Box<dyn core::any::Any>>,
+}
+
+impl InherentData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `data` under `identifier`, replacing any previous data.
+    pub fn put_data<D: 'static>(&mut self, identifier: InherentIdentifier, data: D) {
+        self.data.insert(identifier, Box::new(data));
+    }
+
+    /// The data stored under `identifier`, if there is some of type `D`.
+    pub fn get_data<D: Clone + 'static>(&self, identifier: &InherentIdentifier) -> Option<D> {
+        self.data.get(identifier).and_then(|data| data.downcast_ref::<D>()).cloned()
+    }
+}
+
+/// Implemented by pallets which have an inherent: an unsigned call the block author includes in
+/// a block, like setting the timestamp. Pallets are marked with `#[inherent]` in the runtime for
+/// their inherents to be created and checked.
+pub trait ProvideInherent {
+    /// The call type of the pallet.
+    type Call;
+
+    /// Create the inherent for a new block from `data`, if there is one.
+    fn create_inherent(data: &InherentData) -> Option<Self::Call>;
+
+    /// Whether `call` is an inherent of this pallet. Only inherents can be unsigned extrinsics.
+    fn is_inherent(call: &Self::Call) -> bool;
+
+    /// Whether every block must include an inherent of this pallet.
+    fn is_inherent_required() -> bool {
+        false
+    }
+
+    /// Check the inherent of a block against our own `data`, for example to reject a block whose
+    /// timestamp is too far in the future.
+    fn check_inherent(_call: &Self::Call, _data: &InherentData) -> DispatchResult {
+        Ok(())
+    }
+}
+
+/// A measure of the computation used while executing a block.
+pub type Weight = u64;
+
+/// Entry points of a pallet which are called by the runtime in every block, for each pallet in the
+/// order they are declared in the runtime. All functions do nothing by default.
+///
+/// Hooks are given the whole runtime `T`, like calls taking `runtime: &mut T`.
+pub trait Hooks<T: crate::system::Config> {
+    /// Called at the start of each block, before any extrinsic is dispatched. Returns the weight
+    /// used.
+    fn on_initialize(_runtime: &mut T, _block_number: T::BlockNumber) -> Weight {
+        0
+    }
+
+    /// Called after all extrinsics are dispatched, with the weight left in the block. Useful for
+    /// work which can be postponed, like cleaning up storage. Returns the weight used, which should
+    /// not be more than `remaining_weight`.
+    fn on_idle(_runtime: &mut T, _remaining_weight: Weight) -> Weight {
+        0
+    }
+
+    /// Called at the end of each block, after `on_idle`.
+    fn on_finalize(_runtime: &mut T, _block_number: T::BlockNumber) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    thread_local! {
+        // The hooks and calls of the `recorder` pallets, in the order they were called.
+        static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // A pallet recording when its hooks and its call are made. `on_idle` uses part of the weight
+    // it is given.
+    mod recorder {
+        use super::LOG;
+        use crate::support::{ DispatchResult, Hooks, Instance, Weight };
+
+        pub trait Config<I: Instance = ()>: crate::system::Config {
+            const NAME: &'static str;
+            const IDLE_WEIGHT: Weight;
+        }
+
+        #[derive(Debug)]
+        pub struct Pallet<T: Config<I>, I: Instance = ()>(core::marker::PhantomData<(T, I)>);
+
+        impl<T: Config<I>, I: Instance> Pallet<T, I> {
+            pub fn new(_store: &crate::storage::Store) -> Self {
+                Self(core::marker::PhantomData)
+            }
+
+            fn record(event: String) {
+                LOG.with(|log| log.borrow_mut().push(format!("{} {}", T::NAME, event)));
+            }
+        }
+
+        impl<T: Config<I>, I: Instance> Hooks<T> for Pallet<T, I> {
+            fn on_initialize(_runtime: &mut T, block_number: T::BlockNumber) -> Weight {
+                Self::record(format!("on_initialize {:?}", block_number));
+                5
+            }
+
+            fn on_idle(_runtime: &mut T, remaining_weight: Weight) -> Weight {
+                Self::record(format!("on_idle {}", remaining_weight));
+                T::IDLE_WEIGHT
+            }
+
+            fn on_finalize(_runtime: &mut T, block_number: T::BlockNumber) {
+                Self::record(format!("on_finalize {:?}", block_number));
+            }
+        }
+
+        #[macros::call]
+        impl<T: Config<I>, I: Instance> Pallet<T, I> {
+            #[call_index(0)]
+            pub fn call(&mut self, _caller: T::AccountId) -> DispatchResult {
+                Self::record("call".to_string());
+                Ok(())
+            }
+        }
+    }
+
+    // A runtime with two `recorder` pallets. Not all the functions generated for it are used.
+    #[allow(dead_code)]
+    mod runtime {
+        use super::recorder;
+        use crate::{ support::{ self, Dispatch }, system };
+
+        pub mod types {
+            pub type Extrinsic = crate::support::Extrinsic<String, super::RuntimeCall>;
+            pub type Header = crate::support::Header<u32>;
+            pub type Block = crate::support::Block<Header, Extrinsic>;
+        }
+
+        #[macros::runtime]
+        pub struct Runtime {
+            system: system::Pallet<Self>,
+            first: recorder::Pallet<Self>,
+            #[pallet_index(1)]
+            second: recorder::Pallet<Self, support::Instance2>,
+        }
+
+        impl system::Config for Runtime {
+            type AccountId = String;
+            type BlockNumber = u32;
+            type Nonce = u32;
+            type BaseCallFilter = ();
+
+            const MAX_BLOCK_WEIGHT: support::Weight = 100;
+            const EXTRINSIC_WEIGHT: support::Weight = 10;
+        }
+
+        impl recorder::Config for Runtime {
+            const NAME: &'static str = "first";
+            const IDLE_WEIGHT: support::Weight = 30;
+        }
+
+        impl recorder::Config<support::Instance2> for Runtime {
+            const NAME: &'static str = "second";
+            const IDLE_WEIGHT: support::Weight = 0;
+        }
+
+        #[test]
+        fn hooks_run_around_the_extrinsics_in_declaration_order() {
+            let mut runtime = Runtime::new();
+            let block = types::Block {
+                header: support::Header { block_number: 1 },
+                extrinsics: vec![
+                    types::Extrinsic::signed(
+                        "alice".to_string(),
+                        recorder::Call::<Runtime>::new_call()
+                    )
+                ],
+            };
+            assert_eq!(runtime.execute_block(block), Ok(()));
+
+            // Each `on_initialize` and the extrinsic use 5 and 10 of the 100 the block can use,
+            // so `on_idle` of the first pallet is given 80, and uses 30 of it.
+            let log = super::LOG.with(|log| log.take());
+            assert_eq!(log, [
+                "first on_initialize 1",
+                "second on_initialize 1",
+                "first call",
+                "first on_idle 80",
+                "second on_idle 50",
+                "first on_finalize 1",
+                "second on_finalize 1",
+            ]);
+            assert_eq!(runtime.system.block_weight(), 50);
+        }
+
+        #[test]
+        fn implicit_pallet_indices_skip_explicit_ones() {
+            let metadata = Runtime::metadata();
+            let index = |name| metadata.pallet(name).unwrap().index;
+            assert_eq!((index("system"), index("first"), index("second")), (0, 2, 1));
+
+            // The recorder has no storage items or constants to describe.
+            let first = metadata.pallet("first").unwrap();
+            assert!(first.storage.is_empty() && first.constants.is_empty());
+        }
+    }
+}
@@ -1,45 +1,241 @@
-use std::{ collections::BTreeMap, ops::AddAssign };
-use num::traits::{ Zero, One };
+use core::fmt::Debug;
+use std::ops::AddAssign;
+use num::traits::{ CheckedAdd, CheckedSub, One, Zero };
+use crate::metadata::{ ArgMetadata, ConstantMetadata, EventMetadata };
+use crate::storage::{ Decode, Encode, StorageMap, StorageValue, Store };
+use crate::support::{ DispatchResult, Origin, Weight };
+
+/// The key the code of the runtime is stored at, by `set_code`.
+pub const CODE: &[u8] = b":code";
 
 pub trait Config {
-    type AccountId: Ord + Clone;
-    type BlockNumber: Zero + One + AddAssign + Copy;
-    type Nonce: Zero + One + Copy;
+    type AccountId: Ord + Clone + Encode + Decode + Debug;
+    type BlockNumber: Zero +
+        One +
+        AddAssign +
+        CheckedAdd +
+        CheckedSub +
+        Ord +
+        Copy +
+        Encode +
+        Decode +
+        Debug;
+    type Nonce: Zero + One + Copy + Encode + Decode + Debug;
+    /// Decides which calls can be dispatched, by implementing `support::Contains` for the runtime
+    /// and its `RuntimeCall`. Calls it does not contain fail, unless they are dispatched by root.
+    type BaseCallFilter;
+
+    /// The maximum weight which can be used by a block. Whatever is left after dispatching all
+    /// extrinsics is offered to the `on_idle` hook of each pallet.
+    const MAX_BLOCK_WEIGHT: Weight;
+    /// The weight used by dispatching a single extrinsic.
+    const EXTRINSIC_WEIGHT: Weight;
 }
 
+/// An event deposited by a call, which can be read back until the next block starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId> {
+    /// `sender` made a remark with this hash.
+    Remarked { sender: AccountId, hash: [u8; 8] },
+}
+
+impl<AccountId: Encode> Encode for Event<AccountId> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Event::Remarked { sender, hash } => {
+                dest.push(0);
+                sender.encode_to(dest);
+                hash.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<AccountId: Decode> Decode for Event<AccountId> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => {
+                let sender = Decode::decode(input)?;
+                Some(Event::Remarked { sender, hash: Decode::decode(input)? })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[macros::storage]
 #[derive(Debug)]
 pub struct Pallet<T: Config> {
-    block_number: T::BlockNumber,
-    nonce: BTreeMap<T::AccountId, T::Nonce>,
+    /// The number of the current block.
+    block_number: StorageValue<T::BlockNumber>,
+    /// The weight used so far in the current block.
+    block_weight: StorageValue<Weight>,
+    /// The number of transactions made by each account.
+    nonce: StorageMap<T::AccountId, T::Nonce>,
+    /// The events deposited in the current block.
+    events: StorageValue<Vec<Event<T::AccountId>>>,
 }
 
 impl<T: Config> Pallet<T> {
-    pub fn new() -> Self {
-        Self {
-            block_number: T::BlockNumber::zero(),
-            nonce: BTreeMap::new(),
-        }
+    /// The store shared by all the pallets of the runtime, for example to enumerate or hash the
+    /// whole state.
+    pub fn storage(&self) -> &Store {
+        self.block_number.store()
     }
 
     pub fn block_number(&self) -> T::BlockNumber {
-        self.block_number
+        self.block_number.get().unwrap_or(T::BlockNumber::zero())
     }
 
+    // Start a new block, by incrementing the block number and resetting the weight used and the
+    // events.
     pub fn inc_block_number(&mut self) {
-        self.block_number += T::BlockNumber::one();
+        let mut block_number = self.block_number();
+        block_number += T::BlockNumber::one();
+        self.block_number.put(&block_number);
+        self.block_weight.kill();
+        self.events.kill();
+    }
+
+    pub fn events(&self) -> Vec<Event<T::AccountId>> {
+        self.events.get().unwrap_or_default()
+    }
+
+    // Add an event to the events of the current block.
+    pub fn deposit_event(&mut self, event: Event<T::AccountId>) {
+        let mut events = self.events();
+        events.push(event);
+        self.events.put(&events);
+    }
+
+    pub fn block_weight(&self) -> Weight {
+        self.block_weight.get().unwrap_or(0)
+    }
+
+    // The weight which can still be used in the current block.
+    pub fn remaining_weight(&self) -> Weight {
+        T::MAX_BLOCK_WEIGHT.saturating_sub(self.block_weight())
+    }
+
+    // Add to the weight used in the current block.
+    pub fn register_weight(&mut self, weight: Weight) {
+        let block_weight = self.block_weight().saturating_add(weight);
+        self.block_weight.put(&block_weight);
     }
 
     // Increment the nonce of an account. This helps us keep track of how many transactions each
     // account has made.
     pub fn inc_nonce(&mut self, who: &T::AccountId) {
-        let nonce = *self.nonce.get(who).unwrap_or(&T::Nonce::zero());
+        let nonce = self.nonce.get(who).unwrap_or(T::Nonce::zero());
         let new_nonce = nonce + T::Nonce::one();
-        self.nonce.insert(who.clone(), new_nonce);
+        self.nonce.insert(who, &new_nonce);
+    }
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("MAX_BLOCK_WEIGHT", T::MAX_BLOCK_WEIGHT),
+            ConstantMetadata::new("EXTRINSIC_WEIGHT", T::EXTRINSIC_WEIGHT)
+        ]
+    }
+}
+
+impl<T: Config> crate::metadata::PalletEventMetadata for Pallet<T> {
+    fn event_metadata() -> Vec<EventMetadata> {
+        vec![
+            EventMetadata {
+                name: "Remarked",
+                index: 0,
+                fields: vec![
+                    ArgMetadata { name: "sender", ty: core::any::type_name::<T::AccountId>() },
+                    ArgMetadata { name: "hash", ty: core::any::type_name::<[u8; 8]>() }
+                ],
+                docs: vec!["`sender` made a remark with this hash."],
+            }
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Make a remark on chain. This does nothing, besides using the nonce of the caller.
+    #[call_index(0)]
+    pub fn remark(&mut self, _caller: T::AccountId, _remark: Vec<u8>) -> DispatchResult {
+        Ok(())
+    }
+
+    /// Set raw values in storage. Only root can make this call.
+    #[call_index(1)]
+    pub fn set_storage(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        items: Vec<(Vec<u8>, Vec<u8>)>
+    ) -> DispatchResult {
+        origin.ensure_root()?;
+        for (key, value) in items {
+            self.storage().insert(key, value);
+        }
+        Ok(())
+    }
+
+    /// Remove raw keys from storage. Only root can make this call.
+    #[call_index(2)]
+    pub fn kill_storage(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        keys: Vec<Vec<u8>>
+    ) -> DispatchResult {
+        origin.ensure_root()?;
+        for key in keys {
+            self.storage().remove(&key);
+        }
+        Ok(())
+    }
+
+    /// Remove all the keys starting with `prefix` from storage, like all the items of a pallet.
+    /// Only root can make this call.
+    #[call_index(3)]
+    pub fn kill_prefix(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        prefix: Vec<u8>
+    ) -> DispatchResult {
+        origin.ensure_root()?;
+        for (key, _) in self.storage().iter_prefix(&prefix) {
+            self.storage().remove(&key);
+        }
+        Ok(())
+    }
+
+    /// Set the code of the runtime, stored at `CODE`, to upgrade it. Only root can make this call.
+    ///
+    /// This only checks that the code is not empty. Nothing checks that it is a runtime, or that
+    /// its version is newer than the current one, so root must make sure the upgrade is valid.
+    #[call_index(4)]
+    pub fn set_code(&mut self, origin: Origin<T::AccountId>, code: Vec<u8>) -> DispatchResult {
+        origin.ensure_root()?;
+        if code.is_empty() {
+            return Err("The code cannot be empty");
+        }
+        self.storage().insert(CODE.to_vec(), code);
+        Ok(())
+    }
+
+    /// Make a remark on chain, and deposit an event with its hash.
+    #[call_index(5)]
+    pub fn remark_with_event(&mut self, caller: T::AccountId, remark: Vec<u8>) -> DispatchResult {
+        let hash = crate::storage::hash(&remark);
+        self.deposit_event(Event::Remarked { sender: caller, hash });
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::storage::Store;
     use crate::system::Pallet;
     use crate::types::{ AccountId, BlockNumber, Nonce };
 
@@ -48,15 +244,76 @@ mod test {
         type AccountId = AccountId;
         type BlockNumber = BlockNumber;
         type Nonce = Nonce;
+        type BaseCallFilter = ();
+
+        const MAX_BLOCK_WEIGHT: crate::support::Weight = 100;
+        const EXTRINSIC_WEIGHT: crate::support::Weight = 10;
     }
 
     #[test]
     fn init_system() {
-        let mut system = Pallet::<TestConfig>::new();
+        let mut system = Pallet::<TestConfig>::new(&Store::new());
         system.inc_block_number();
         system.inc_nonce(&"alice".to_string());
 
         assert_eq!(system.block_number(), 1);
-        assert_eq!(system.nonce.get(&"alice".to_string()), Some(&1));
+        assert_eq!(system.nonce.get(&"alice".to_string()), Some(1));
+    }
+
+    #[test]
+    fn block_weight() {
+        let mut system = Pallet::<TestConfig>::new(&Store::new());
+        system.register_weight(30);
+        assert_eq!(system.block_weight(), 30);
+        assert_eq!(system.remaining_weight(), 70);
+
+        system.register_weight(80);
+        assert_eq!(system.remaining_weight(), 0);
+
+        system.inc_block_number();
+        assert_eq!(system.block_weight(), 0);
+    }
+
+    #[test]
+    fn root_storage_calls() {
+        use crate::support::Origin;
+
+        let mut system = Pallet::<TestConfig>::new(&Store::new());
+        let items = vec![
+            (b"a:1".to_vec(), vec![1]),
+            (b"a:2".to_vec(), vec![2]),
+            (b"b".to_vec(), vec![3])
+        ];
+        assert_eq!(
+            system.set_storage(Origin::Signed("alice".to_string()), items.clone()),
+            Err("Expected the root origin")
+        );
+        assert_eq!(system.set_storage(Origin::Root, items), Ok(()));
+        assert_eq!(system.storage().get(b"a:1"), Some(vec![1]));
+
+        assert_eq!(system.kill_prefix(Origin::Root, b"a:".to_vec()), Ok(()));
+        assert_eq!(system.storage().get(b"a:1"), None);
+        assert_eq!(system.storage().get(b"a:2"), None);
+        assert_eq!(system.kill_storage(Origin::Root, vec![b"b".to_vec()]), Ok(()));
+        assert_eq!(system.storage().get(b"b"), None);
+
+        assert_eq!(system.set_code(Origin::Root, Vec::new()), Err("The code cannot be empty"));
+        assert_eq!(system.set_code(Origin::Root, vec![0, 1]), Ok(()));
+        assert_eq!(system.storage().get(super::CODE), Some(vec![0, 1]));
+    }
+
+    #[test]
+    fn remark_with_event() {
+        let alice = "alice".to_string();
+        let mut system = Pallet::<TestConfig>::new(&Store::new());
+        system.inc_block_number();
+
+        assert_eq!(system.remark_with_event(alice.clone(), b"hello".to_vec()), Ok(()));
+        let hash = crate::storage::hash(b"hello");
+        assert_eq!(system.events(), vec![super::Event::Remarked { sender: alice, hash }]);
+
+        // Events only last for the block they were deposited in.
+        system.inc_block_number();
+        assert_eq!(system.events(), vec![]);
     }
 }
@@ -0,0 +1,200 @@
+use core::fmt::Debug;
+use num::traits::{ CheckedAdd, Zero };
+use crate::metadata::ConstantMetadata;
+use crate::storage::{ Decode, Encode, StorageValue };
+use crate::support::{ DispatchResult, InherentData, InherentIdentifier, Origin, ProvideInherent };
+
+/// The key of the current time in the `InherentData` of the block author.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = "timstap0";
+
+pub trait Config: Sized + crate::system::Config + AsMut<Pallet<Self>> {
+    /// A point in time, for example in milliseconds since the unix epoch.
+    type Moment: Zero + CheckedAdd + Ord + Copy + Encode + Decode + Debug + 'static;
+
+    /// The minimum time between the timestamps of two blocks.
+    const MINIMUM_PERIOD: Self::Moment;
+    /// How far the timestamp of a block can be ahead of our own time.
+    const MAX_DRIFT: Self::Moment;
+}
+
+/// This pallet keeps the time of the current block, which the block author sets with an inherent.
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The time of the current block.
+    now: StorageValue<T::Moment>,
+    /// Whether the timestamp was set in the current block.
+    did_update: StorageValue<bool>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// The time of the current block, once it has been set.
+    pub fn now(&self) -> T::Moment {
+        self.now.get().unwrap_or(T::Moment::zero())
+    }
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("MINIMUM_PERIOD", T::MINIMUM_PERIOD),
+            ConstantMetadata::new("MAX_DRIFT", T::MAX_DRIFT)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {
+    fn on_finalize(runtime: &mut T, _block_number: T::BlockNumber) {
+        let timestamp: &mut Pallet<T> = runtime.as_mut();
+        timestamp.did_update.kill();
+    }
+}
+
+impl<T: Config> ProvideInherent for Pallet<T> {
+    type Call = Call<T>;
+
+    fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+        let now = data.get_data::<T::Moment>(&INHERENT_IDENTIFIER)?;
+        Some(Call::set { now })
+    }
+
+    fn is_inherent(call: &Self::Call) -> bool {
+        matches!(call, Call::set { .. })
+    }
+
+    // Every block must have a timestamp.
+    fn is_inherent_required() -> bool {
+        true
+    }
+
+    fn check_inherent(call: &Self::Call, data: &InherentData) -> DispatchResult {
+        let Call::set { now } = call;
+        let our_now = data
+            .get_data::<T::Moment>(&INHERENT_IDENTIFIER)
+            .ok_or("Timestamp inherent data not found")?;
+        let latest = our_now.checked_add(&T::MAX_DRIFT).ok_or("Overflow")?;
+        if *now > latest {
+            return Err("Timestamp is too far in the future");
+        }
+        Ok(())
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Set the time of the current block. This must be an unsigned extrinsic, included once in
+    /// every block, at least `MINIMUM_PERIOD` after the time of the previous block.
+    #[call_index(0)]
+    pub fn set(&mut self, origin: Origin<T::AccountId>, now: T::Moment) -> DispatchResult {
+        origin.ensure_none()?;
+        if self.did_update.get().unwrap_or(false) {
+            return Err("Timestamp must be updated only once in the block");
+        }
+        let earliest = self.now().checked_add(&T::MINIMUM_PERIOD).ok_or("Overflow")?;
+        if now < earliest {
+            return Err("Timestamp must increment by at least the minimum period");
+        }
+        self.now.put(&now);
+        self.did_update.put(&true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::INHERENT_IDENTIFIER;
+    use crate::{ support::{ self, Origin }, testing::block, types, Runtime, RuntimeCall };
+
+    #[test]
+    fn set_timestamp() {
+        let mut runtime = Runtime::new();
+
+        assert_eq!(runtime.execute_block(block(1, 6_000, vec![])), Ok(()));
+        assert_eq!(runtime.timestamp.now(), 6_000);
+
+        let mut twice = block(2, 12_000, vec![]);
+        twice.extrinsics.push(support::Extrinsic {
+            caller: None,
+            call: RuntimeCall::timestamp(super::Call::set { now: 18_000 }),
+        });
+        assert_eq!(
+            runtime.execute_block(twice),
+            Err("Timestamp must be updated only once in the block")
+        );
+        // The failed block changed nothing, so the next block can still set the timestamp.
+        assert_eq!(runtime.system.block_number(), 1);
+        assert_eq!(runtime.timestamp.now(), 6_000);
+        assert_eq!(runtime.execute_block(block(2, 12_000, vec![])), Ok(()));
+
+        // A block whose timestamp is too early is invalid.
+        let mut runtime = Runtime::new();
+        assert_eq!(runtime.execute_block(block(1, 6_000, vec![])), Ok(()));
+        assert_eq!(
+            runtime.execute_block(block(2, 8_000, vec![])),
+            Err("Timestamp must increment by at least the minimum period")
+        );
+    }
+
+    #[test]
+    fn timestamp_must_be_unsigned_and_first() {
+        let mut runtime = Runtime::new();
+        assert_eq!(
+            runtime.timestamp.set(Origin::Signed("alice".to_string()), 6_000),
+            Err("Expected no origin")
+        );
+
+        let mut block = block(1, 6_000, vec![]);
+        block.extrinsics.insert(0, support::Extrinsic {
+            caller: Some("alice".to_string()),
+            call: RuntimeCall::balances(crate::balances::Call::transfer {
+                to: "bob".to_string(),
+                amount: 0,
+            }),
+        });
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("unsigned extrinsics must come before signed extrinsics")
+        );
+        // The block was rejected before the block number or the nonce of alice changed.
+        assert_eq!(runtime.system.block_number(), 0);
+        assert_eq!(runtime.system.storage().iter_prefix(&[]), vec![]);
+    }
+
+    #[test]
+    fn inherents_are_created_and_checked() {
+        let mut runtime = Runtime::new();
+        let mut data = support::InherentData::new();
+        data.put_data(INHERENT_IDENTIFIER, 60_000u64);
+
+        let block = types::Block {
+            header: support::Header { block_number: 1 },
+            extrinsics: Runtime::create_inherents(&data),
+        };
+        assert_eq!(Runtime::check_inherents(&block, &data), Ok(()));
+
+        // Our own time is too far behind the timestamp of the block.
+        let mut late_data = support::InherentData::new();
+        late_data.put_data(INHERENT_IDENTIFIER, 0u64);
+        assert_eq!(
+            Runtime::check_inherents(&block, &late_data),
+            Err("Timestamp is too far in the future")
+        );
+
+        // Every block needs a timestamp, and only inherents can be unsigned.
+        let empty = types::Block {
+            header: support::Header { block_number: 1 },
+            extrinsics: vec![],
+        };
+        assert_eq!(runtime.execute_block(empty), Err("a required inherent is missing"));
+        let mut unsigned = block;
+        unsigned.extrinsics.push(support::Extrinsic {
+            caller: None,
+            call: RuntimeCall::balances(crate::balances::Call::transfer {
+                to: "bob".to_string(),
+                amount: 0,
+            }),
+        });
+        let mut runtime = Runtime::new();
+        assert_eq!(runtime.execute_block(unsigned), Err("unsigned extrinsic is not an inherent"));
+    }
+}
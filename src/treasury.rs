@@ -0,0 +1,305 @@
+use num::traits::{ CheckedAdd, CheckedDiv, CheckedMul, Zero };
+use crate::balances;
+use crate::metadata::ConstantMetadata;
+use crate::storage::{ Decode, Encode, StorageMap, StorageValue };
+use crate::support::{ Convert, DispatchResult, EnsureOrigin, Origin, PalletId, Weight };
+
+/// The index of a spend proposal.
+pub type ProposalIndex = u32;
+
+pub trait Config: Sized +
+    crate::balances::Config +
+    AsMut<crate::balances::Pallet<Self>> +
+    AsMut<Pallet<Self>> {
+    /// Derives the treasury account from `PALLET_ID`.
+    type PalletIdToAccountId: Convert<PalletId, Self::AccountId>;
+    /// The origin which can approve or reject spend proposals.
+    type ApproveOrigin: EnsureOrigin<Self::AccountId>;
+
+    /// The id the treasury account is derived from.
+    const PALLET_ID: PalletId;
+    /// The percentage of the proposed value which the proposer must reserve as a bond.
+    const PROPOSAL_BOND_PERCENT: u32;
+    /// The minimum bond of a proposal.
+    const PROPOSAL_BOND_MINIMUM: Self::Balance;
+    /// The number of blocks between paying out approved proposals.
+    const SPEND_PERIOD: Self::BlockNumber;
+    /// The percentage of the funds left after a spend period which is burned, when every approved
+    /// proposal was paid out. Zero burns nothing.
+    const BURN_PERCENT: u32;
+}
+
+/// A proposal to pay `value` from the treasury to `beneficiary`.
+#[derive(Debug)]
+pub struct Proposal<T: Config> {
+    pub proposer: T::AccountId,
+    pub value: T::Balance,
+    pub beneficiary: T::AccountId,
+    /// The funds reserved from the proposer, which are lost if the proposal is rejected.
+    pub bond: T::Balance,
+}
+
+impl<T: Config> Encode for Proposal<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.proposer.encode_to(dest);
+        self.value.encode_to(dest);
+        self.beneficiary.encode_to(dest);
+        self.bond.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Proposal<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            proposer: Decode::decode(input)?,
+            value: Decode::decode(input)?,
+            beneficiary: Decode::decode(input)?,
+            bond: Decode::decode(input)?,
+        })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The spend proposals which were not paid out or rejected yet.
+    proposals: StorageMap<ProposalIndex, Proposal<T>>,
+    /// The number of proposals ever made.
+    proposal_count: StorageValue<ProposalIndex>,
+    /// The approved proposals, in the order they are paid out.
+    approvals: StorageValue<Vec<ProposalIndex>>,
+    /// The block the funds of the treasury were last spent at.
+    last_spend: StorageValue<T::BlockNumber>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// The account which holds the funds of the treasury.
+    pub fn account_id() -> T::AccountId {
+        T::PalletIdToAccountId::convert(T::PALLET_ID)
+    }
+
+    pub fn proposal(&self, index: ProposalIndex) -> Option<Proposal<T>> {
+        self.proposals.get(&index)
+    }
+
+    /// The approved proposals which have not been paid out yet.
+    pub fn approvals(&self) -> Vec<ProposalIndex> {
+        self.approvals.get().unwrap_or_default()
+    }
+
+    // Pay out the approved proposals the treasury can afford, in the order they were approved. Once
+    // they are all paid, part of what is left is burned.
+    fn spend_funds(runtime: &mut T) {
+        let account = Self::account_id();
+        let treasury: &mut Pallet<T> = runtime.as_mut();
+        let approvals = treasury.approvals();
+
+        let mut unpaid = Vec::new();
+        for index in approvals {
+            let treasury: &mut Pallet<T> = runtime.as_mut();
+            let Some(proposal) = treasury.proposal(index) else {
+                continue;
+            };
+
+            let balances: &mut balances::Pallet<T> = runtime.as_mut();
+            let beneficiary = proposal.beneficiary.clone();
+            let paid = proposal.value <= balances.balance(&account) &&
+                balances.transfer(account.clone(), beneficiary, proposal.value).is_ok();
+            if paid {
+                balances.unreserve(&proposal.proposer, proposal.bond);
+                let treasury: &mut Pallet<T> = runtime.as_mut();
+                treasury.proposals.remove(&index);
+            } else {
+                // The proposal stays approved, until the treasury has enough funds.
+                unpaid.push(index);
+            }
+        }
+        let treasury: &mut Pallet<T> = runtime.as_mut();
+        let all_paid = unpaid.is_empty();
+        treasury.approvals.put(&unpaid);
+        // The funds are only unspent when no approved proposal is waiting for them.
+        if !all_paid {
+            return;
+        }
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        let burn = percent_of::<T>(balances.balance(&account), T::BURN_PERCENT);
+        balances.burn(&account, burn);
+    }
+}
+
+// `percent` percent of `value`, rounded down.
+fn percent_of<T: Config>(value: T::Balance, percent: u32) -> T::Balance {
+    let hundred = T::Balance::from(100);
+    let percent = T::Balance::from(percent);
+    match value.checked_mul(&percent) {
+        Some(product) => product.checked_div(&hundred),
+        // Dividing first is less precise, but cannot overflow for percentages up to 100.
+        None => value.checked_div(&hundred).and_then(|value| value.checked_mul(&percent)),
+    }.unwrap_or(value)
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("PALLET_ID", T::PALLET_ID),
+            ConstantMetadata::new("PROPOSAL_BOND_PERCENT", T::PROPOSAL_BOND_PERCENT),
+            ConstantMetadata::new("PROPOSAL_BOND_MINIMUM", T::PROPOSAL_BOND_MINIMUM),
+            ConstantMetadata::new("SPEND_PERIOD", T::SPEND_PERIOD),
+            ConstantMetadata::new("BURN_PERCENT", T::BURN_PERCENT)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {
+    // Spend the funds of the treasury every `SPEND_PERIOD` blocks.
+    fn on_initialize(runtime: &mut T, block_number: T::BlockNumber) -> Weight {
+        let treasury: &mut Pallet<T> = runtime.as_mut();
+        let last_spend = treasury.last_spend.get().unwrap_or(T::BlockNumber::zero());
+        let next_spend = last_spend.checked_add(&T::SPEND_PERIOD);
+        if next_spend.is_some_and(|next_spend| block_number >= next_spend) {
+            treasury.last_spend.put(&block_number);
+            Self::spend_funds(runtime);
+        }
+        0
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Propose to pay `value` from the treasury to `beneficiary`. The caller reserves a bond of
+    /// `PROPOSAL_BOND_PERCENT` of `value`, and at least `PROPOSAL_BOND_MINIMUM`.
+    #[call_index(0)]
+    pub fn propose_spend(
+        runtime: &mut T,
+        caller: T::AccountId,
+        value: T::Balance,
+        beneficiary: T::AccountId
+    ) -> DispatchResult {
+        if value.is_zero() {
+            return Err("Cannot propose to spend zero");
+        }
+        let bond = percent_of::<T>(value, T::PROPOSAL_BOND_PERCENT).max(T::PROPOSAL_BOND_MINIMUM);
+        let treasury: &mut Pallet<T> = runtime.as_mut();
+        let index = treasury.proposal_count.get().unwrap_or(0);
+        let proposal_count = index.checked_add(1).ok_or("Overflow")?;
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.reserve(&caller, bond)?;
+
+        let treasury: &mut Pallet<T> = runtime.as_mut();
+        treasury.proposal_count.put(&proposal_count);
+        treasury.proposals.insert(&index, &Proposal { proposer: caller, value, beneficiary, bond });
+        Ok(())
+    }
+
+    /// Reject the proposal at `proposal_id`. Its bond is moved to the treasury.
+    #[call_index(1)]
+    pub fn reject_proposal(
+        runtime: &mut T,
+        origin: Origin<T::AccountId>,
+        proposal_id: ProposalIndex
+    ) -> DispatchResult {
+        T::ApproveOrigin::ensure_origin(origin)?;
+        let treasury: &mut Pallet<T> = runtime.as_mut();
+        let proposal = treasury.proposal(proposal_id).ok_or("Proposal does not exist")?;
+        let (proposer, bond) = (proposal.proposer.clone(), proposal.bond);
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.repatriate_reserved(&proposer, &Self::account_id(), bond)?;
+
+        let treasury: &mut Pallet<T> = runtime.as_mut();
+        treasury.proposals.remove(&proposal_id);
+        let mut approvals = treasury.approvals();
+        approvals.retain(|index| *index != proposal_id);
+        treasury.approvals.put(&approvals);
+        Ok(())
+    }
+
+    /// Approve the proposal at `proposal_id`, to be paid out at the next spend period.
+    #[call_index(2)]
+    pub fn approve_proposal(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        proposal_id: ProposalIndex
+    ) -> DispatchResult {
+        T::ApproveOrigin::ensure_origin(origin)?;
+        if self.proposal(proposal_id).is_none() {
+            return Err("Proposal does not exist");
+        }
+        let mut approvals = self.approvals();
+        if approvals.contains(&proposal_id) {
+            return Err("Proposal is already approved");
+        }
+        approvals.push(proposal_id);
+        self.approvals.put(&approvals);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pallet;
+    use crate::{ support::Origin, testing::run_to_block, Runtime };
+
+    #[test]
+    fn rejected_proposal_loses_bond() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let treasury = Pallet::<Runtime>::account_id();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(Pallet::propose_spend(&mut runtime, alice.clone(), 60, bob.clone()), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 3);
+
+        assert_eq!(
+            Pallet::reject_proposal(&mut runtime, Origin::Signed(bob), 0),
+            Err("Expected the root origin")
+        );
+        assert_eq!(Pallet::reject_proposal(&mut runtime, Origin::Root, 0), Ok(()));
+        assert!(runtime.treasury.proposal(0).is_none());
+        assert_eq!(runtime.balances.balance(&alice), 97);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(runtime.balances.balance(&treasury), 3);
+    }
+
+    #[test]
+    fn approved_proposals_are_paid_out() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let charlie = "charlie".to_string();
+        let treasury = Pallet::<Runtime>::account_id();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&treasury, 100);
+
+        assert_eq!(Pallet::propose_spend(&mut runtime, alice.clone(), 60, bob.clone()), Ok(()));
+        assert_eq!(Pallet::propose_spend(&mut runtime, alice.clone(), 50, charlie.clone()), Ok(()));
+        assert_eq!(runtime.treasury.approve_proposal(Origin::Root, 0), Ok(()));
+        assert_eq!(runtime.treasury.approve_proposal(Origin::Root, 1), Ok(()));
+        assert_eq!(
+            runtime.treasury.approve_proposal(Origin::Root, 1),
+            Err("Proposal is already approved")
+        );
+
+        // Only the first proposal can be paid out, and nothing is burned while the second waits.
+        run_to_block(&mut runtime, 4);
+        assert_eq!(runtime.balances.balance(&bob), 60);
+        assert_eq!(runtime.balances.balance(&charlie), 0);
+        assert_eq!(runtime.balances.balance(&treasury), 40);
+        assert_eq!(runtime.treasury.approvals(), vec![1]);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 2);
+
+        // Once the treasury is funded, the second proposal is paid out at the next spend period.
+        runtime.balances.set_balance(&treasury, 100);
+        run_to_block(&mut runtime, 8);
+        assert_eq!(runtime.balances.balance(&charlie), 50);
+        assert!(runtime.treasury.approvals().is_empty());
+        // 10% of what is left after paying every approved proposal is burned.
+        assert_eq!(runtime.balances.balance(&treasury), 45);
+        assert_eq!(runtime.balances.balance(&alice), 100);
+    }
+}
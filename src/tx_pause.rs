@@ -0,0 +1,176 @@
+use core::marker::PhantomData;
+use crate::metadata::ConstantMetadata;
+use crate::storage::StorageMap;
+use crate::support::{ Contains, DispatchResult, EnsureOrigin, GetCallName, Origin };
+
+pub trait Config: Sized + crate::system::Config + AsRef<Pallet<Self>> {
+    /// The origin which can pause and unpause calls.
+    type PauseOrigin: EnsureOrigin<Self::AccountId>;
+
+    /// The pallets which cannot be paused, like the ones every block needs.
+    const UNPAUSABLE_PALLETS: &'static [&'static str];
+}
+
+/// Pauses calls by name, for example to stop transfers during an incident. The runtime uses this
+/// pallet as its `BaseCallFilter`, so paused calls fail unless they are dispatched by root.
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The paused calls, by the name of their pallet and the name of the call. A call name of
+    /// `None` pauses every call of the pallet.
+    paused: StorageMap<(String, Option<String>), bool>,
+    _config: PhantomData<T>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// Whether the call `call_name` of the pallet `pallet_name` is paused, on its own or with its
+    /// whole pallet.
+    pub fn is_paused(&self, pallet_name: &str, call_name: &str) -> bool {
+        let pallet_name = pallet_name.to_string();
+        self.paused.get(&(pallet_name.clone(), None)).is_some() ||
+            self.paused.get(&(pallet_name, Some(call_name.to_string()))).is_some()
+    }
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("UNPAUSABLE_PALLETS", T::UNPAUSABLE_PALLETS)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Pause the call `call` of the pallet `pallet`, or every call of the pallet when `call` is
+    /// `None`. Only the `PauseOrigin` can make this call.
+    #[call_index(0)]
+    pub fn pause(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        pallet: String,
+        call: Option<String>
+    ) -> DispatchResult {
+        T::PauseOrigin::ensure_origin(origin)?;
+        if T::UNPAUSABLE_PALLETS.contains(&pallet.as_str()) {
+            return Err("This pallet cannot be paused");
+        }
+        let key = (pallet, call);
+        if self.paused.get(&key).is_some() {
+            return Err("The call is already paused");
+        }
+        self.paused.insert(&key, &true);
+        Ok(())
+    }
+
+    /// Unpause a call or pallet paused with `pause`. Only the `PauseOrigin` can make this call.
+    #[call_index(1)]
+    pub fn unpause(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        pallet: String,
+        call: Option<String>
+    ) -> DispatchResult {
+        T::PauseOrigin::ensure_origin(origin)?;
+        let key = (pallet, call);
+        if self.paused.get(&key).is_none() {
+            return Err("The call is not paused");
+        }
+        self.paused.remove(&key);
+        Ok(())
+    }
+}
+
+/// The calls which are not paused.
+impl<T: Config, C: GetCallName> Contains<T, C> for Pallet<T> {
+    fn contains(runtime: &T, call: &C) -> bool {
+        let tx_pause: &Pallet<T> = runtime.as_ref();
+        !tx_pause.is_paused(call.pallet_name(), call.call_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::support::{ Dispatch, Origin };
+    use crate::{ balances, testing::run_to_block, tx_pause, Runtime, RuntimeCall };
+
+    fn pause_call(pallet: &str, call: Option<&str>) -> RuntimeCall {
+        RuntimeCall::tx_pause(tx_pause::Call::pause {
+            pallet: pallet.to_string(),
+            call: call.map(|call| call.to_string()),
+        })
+    }
+
+    fn transfer() -> RuntimeCall {
+        RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount: 10 })
+    }
+
+    #[test]
+    fn paused_calls_are_filtered() {
+        let alice = Origin::Signed("alice".to_string());
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+
+        assert_eq!(
+            runtime.dispatch(alice.clone(), pause_call("balances", Some("transfer"))),
+            Err("Expected the root origin")
+        );
+        let pause = pause_call("balances", Some("transfer"));
+        assert_eq!(runtime.dispatch(Origin::Root, pause), Ok(()));
+        assert_eq!(
+            runtime.dispatch(Origin::Root, pause_call("balances", Some("transfer"))),
+            Err("The call is already paused")
+        );
+        assert_eq!(
+            runtime.dispatch(alice.clone(), transfer()),
+            Err("call is not allowed by the base call filter")
+        );
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+
+        let unpause = RuntimeCall::tx_pause(tx_pause::Call::unpause {
+            pallet: "balances".to_string(),
+            call: Some("transfer".to_string()),
+        });
+        assert_eq!(runtime.dispatch(Origin::Root, unpause), Ok(()));
+        assert_eq!(runtime.dispatch(alice, transfer()), Ok(()));
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 10);
+    }
+
+    #[test]
+    fn pausing_a_pallet_pauses_all_its_calls() {
+        let alice = Origin::Signed("alice".to_string());
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+
+        assert_eq!(runtime.dispatch(Origin::Root, pause_call("balances", None)), Ok(()));
+        assert!(runtime.tx_pause.is_paused("balances", "transfer"));
+        assert!(!runtime.tx_pause.is_paused("credits", "transfer"));
+        assert_eq!(
+            runtime.dispatch(alice, transfer()),
+            Err("call is not allowed by the base call filter")
+        );
+        // Root is not filtered.
+        assert_eq!(runtime.dispatch(Origin::Root, transfer()), Err("Expected a signed origin"));
+    }
+
+    #[test]
+    fn pallets_every_block_needs_cannot_be_paused() {
+        let mut runtime = Runtime::new();
+        for pallet in ["system", "timestamp", "tx_pause"] {
+            assert_eq!(
+                runtime.dispatch(Origin::Root, pause_call(pallet, None)),
+                Err("This pallet cannot be paused")
+            );
+        }
+        assert_eq!(runtime.dispatch(Origin::Root, pause_call("balances", None)), Ok(()));
+        run_to_block(&mut runtime, 2);
+        assert_eq!(runtime.system.block_number(), 2);
+
+        // Inherents are not filtered, even when their call is paused.
+        runtime.tx_pause.paused.insert(&("timestamp".to_string(), None), &true);
+        run_to_block(&mut runtime, 3);
+        assert_eq!(runtime.timestamp.now(), 18_000);
+    }
+}
@@ -0,0 +1,255 @@
+use num::traits::{ CheckedAdd, CheckedMul, CheckedSub, Zero };
+use crate::{ balances, metadata::ConstantMetadata, support::{ Convert, DispatchResult } };
+use crate::storage::{ Decode, Encode, StorageMap };
+
+/// The id of the lock this pallet puts on vesting funds in `balances`.
+const VESTING_ID: balances::LockIdentifier = *b"vesting ";
+
+pub trait Config: Sized +
+    crate::balances::Config +
+    AsMut<crate::system::Pallet<Self>> +
+    AsMut<crate::balances::Pallet<Self>> +
+    AsMut<Pallet<Self>> {
+    /// Converts a number of blocks to a balance, to compute how much of a schedule has unlocked.
+    type BlockNumberToBalance: Convert<Self::BlockNumber, Self::Balance>;
+
+    /// The maximum number of vesting schedules an account can have.
+    const MAX_VESTING_SCHEDULES: usize;
+}
+
+/// A schedule of funds which unlock linearly, block by block.
+#[derive(Debug)]
+pub struct VestingInfo<T: Config> {
+    /// The amount locked until `starting_block`.
+    pub locked: T::Balance,
+    /// The amount which unlocks with each block after `starting_block`.
+    pub per_block: T::Balance,
+    pub starting_block: T::BlockNumber,
+}
+
+impl<T: Config> VestingInfo<T> {
+    /// The amount of this schedule which is still locked at block `now`.
+    pub fn locked_at(&self, now: T::BlockNumber) -> T::Balance {
+        let vested_blocks = now.checked_sub(&self.starting_block).unwrap_or(T::BlockNumber::zero());
+        let vested = self.per_block
+            .checked_mul(&T::BlockNumberToBalance::convert(vested_blocks))
+            .unwrap_or(self.locked);
+        self.locked.checked_sub(&vested).unwrap_or(T::Balance::zero())
+    }
+}
+
+impl<T: Config> Encode for VestingInfo<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.locked.encode_to(dest);
+        self.per_block.encode_to(dest);
+        self.starting_block.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for VestingInfo<T> {
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let locked = Decode::decode(input)?;
+        let per_block = Decode::decode(input)?;
+        Some(Self { locked, per_block, starting_block: Decode::decode(input)? })
+    }
+}
+
+#[macros::storage]
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The vesting schedules of each account.
+    vesting: StorageMap<T::AccountId, Vec<VestingInfo<T>>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// The vesting schedules of `who`.
+    pub fn vesting(&self, who: &T::AccountId) -> Vec<VestingInfo<T>> {
+        self.vesting.get(who).unwrap_or_default()
+    }
+
+    // The total amount still locked by the schedules of `who` at block `now`.
+    fn total_locked(
+        &self,
+        who: &T::AccountId,
+        now: T::BlockNumber
+    ) -> Result<T::Balance, &'static str> {
+        self.vesting(who)
+            .iter()
+            .try_fold(T::Balance::zero(), |locked, schedule| {
+                locked.checked_add(&schedule.locked_at(now))
+            })
+            .ok_or("Overflow.")
+    }
+
+    // Update the lock on the funds of `who` to what is still locked by its schedules, removing the
+    // schedules which have fully unlocked.
+    fn update_lock(runtime: &mut T, who: &T::AccountId) -> DispatchResult {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        let now = system.block_number();
+
+        let vesting: &mut Pallet<T> = runtime.as_mut();
+        let locked = vesting.total_locked(who, now)?;
+        let mut schedules = vesting.vesting(who);
+        schedules.retain(|schedule| !schedule.locked_at(now).is_zero());
+        if schedules.is_empty() {
+            vesting.vesting.remove(who);
+        } else {
+            vesting.vesting.insert(who, &schedules);
+        }
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        if locked.is_zero() {
+            balances.remove_lock(VESTING_ID, who);
+        } else {
+            balances.set_lock(VESTING_ID, who, locked);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::metadata::PalletConstantMetadata for Pallet<T> {
+    fn constant_metadata() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("MAX_VESTING_SCHEDULES", T::MAX_VESTING_SCHEDULES)
+        ]
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Unlock the funds of the caller which have vested so far.
+    #[call_index(0)]
+    pub fn vest(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+        let vesting: &mut Pallet<T> = runtime.as_mut();
+        if vesting.vesting.get(&caller).is_none() {
+            return Err("This account has no vesting schedule");
+        }
+        Self::update_lock(runtime, &caller)
+    }
+
+    /// Transfer `locked` to `target`, where it stays locked until `starting_block` and then
+    /// unlocks `per_block` every block.
+    #[call_index(1)]
+    pub fn vested_transfer(
+        runtime: &mut T,
+        caller: T::AccountId,
+        target: T::AccountId,
+        locked: T::Balance,
+        per_block: T::Balance,
+        starting_block: T::BlockNumber
+    ) -> DispatchResult {
+        if locked.is_zero() || per_block.is_zero() {
+            return Err("Invalid vesting schedule");
+        }
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        let now = system.block_number();
+        let vesting: &mut Pallet<T> = runtime.as_mut();
+        if vesting.vesting(&target).len() >= T::MAX_VESTING_SCHEDULES {
+            return Err("Too many vesting schedules");
+        }
+        // Check the new schedule can be added to the lock, before moving any funds.
+        vesting.total_locked(&target, now)?.checked_add(&locked).ok_or("Overflow.")?;
+
+        let balances: &mut balances::Pallet<T> = runtime.as_mut();
+        balances.transfer(caller, target.clone(), locked)?;
+
+        let vesting: &mut Pallet<T> = runtime.as_mut();
+        let mut schedules = vesting.vesting(&target);
+        schedules.push(VestingInfo { locked, per_block, starting_block });
+        vesting.vesting.insert(&target, &schedules);
+        Self::update_lock(runtime, &target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pallet;
+    use crate::Runtime;
+
+    #[test]
+    fn vested_funds_unlock_over_time() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let charlie = "charlie".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 100, 0, 1),
+            Err("Invalid vesting schedule")
+        );
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 100, 10, 1),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&bob), 100);
+        assert_eq!(runtime.balances.locked(&bob), 100);
+        assert_eq!(
+            runtime.balances.transfer(bob.clone(), charlie.clone(), 1),
+            Err("Funds are locked.")
+        );
+
+        // Three blocks after the start, 30 have unlocked.
+        for _ in 0..4 {
+            runtime.system.inc_block_number();
+        }
+        assert_eq!(Pallet::vest(&mut runtime, bob.clone()), Ok(()));
+        assert_eq!(runtime.balances.locked(&bob), 70);
+        assert_eq!(
+            runtime.balances.transfer(bob.clone(), charlie.clone(), 31),
+            Err("Funds are locked.")
+        );
+        assert_eq!(runtime.balances.transfer(bob.clone(), charlie.clone(), 30), Ok(()));
+
+        // Once everything has unlocked, the schedule and the lock are removed.
+        for _ in 0..10 {
+            runtime.system.inc_block_number();
+        }
+        assert_eq!(Pallet::vest(&mut runtime, bob.clone()), Ok(()));
+        assert_eq!(runtime.balances.locked(&bob), 0);
+        assert!(runtime.vesting.vesting(&bob).is_empty());
+        assert_eq!(Pallet::vest(&mut runtime, bob), Err("This account has no vesting schedule"));
+
+        // Vesting your own funds locks them without minting any.
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, charlie.clone(), charlie.clone(), 30, 10, 20),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&charlie), 30);
+        assert_eq!(runtime.balances.locked(&charlie), 30);
+    }
+
+    #[test]
+    fn vesting_schedules_are_capped() {
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&alice, u128::MAX);
+        for _ in 0..3 {
+            assert_eq!(
+                Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 10, 1, 5),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), 10, 1, 5),
+            Err("Too many vesting schedules")
+        );
+
+        // A schedule which would overflow the lock is rejected before any funds move.
+        let charlie = "charlie".to_string();
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, alice.clone(), charlie.clone(), 10, 1, 5),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, alice, charlie.clone(), u128::MAX - 5, 1, 5),
+            Err("Overflow.")
+        );
+        assert_eq!(runtime.balances.balance(&charlie), 10);
+    }
+}